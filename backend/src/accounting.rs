@@ -0,0 +1,134 @@
+use crate::errors::{DomainError, DomainResult};
+use crate::events::DomainEvent;
+use crate::storage::Store;
+use crate::{BalanceEntry, BalanceEntryKind, TradeDirection, User};
+use async_graphql::ID;
+use chrono::Utc;
+use uuid::Uuid;
+
+// ================= Balance Accounting =================
+
+// Margin held for a position (full notional, no leverage)
+pub fn margin_for(entry_price: f64, quantity: f64) -> f64 {
+    entry_price * quantity
+}
+
+// Realized PnL for a position closed at `exit_price`
+pub fn realized_pnl(
+    direction: TradeDirection,
+    entry_price: f64,
+    exit_price: f64,
+    quantity: f64,
+) -> f64 {
+    match direction {
+        TradeDirection::Long => (exit_price - entry_price) * quantity,
+        TradeDirection::Short => (entry_price - exit_price) * quantity,
+    }
+}
+
+// Move `margin` from the user's free balance into used margin.
+// Fails without side effects when the balance cannot cover it.
+pub fn reserve_margin(
+    db: &Store,
+    user_id: &str,
+    margin: f64,
+    reference_id: &str,
+) -> DomainResult<BalanceEntry> {
+    let _accounts = db.lock_accounts();
+    let mut user = db
+        .get_user(user_id)?
+        .ok_or_else(|| DomainError::not_found("User", user_id))?;
+    if user.balance < margin {
+        return Err(DomainError::InsufficientFunds {
+            required: margin,
+            available: user.balance,
+        });
+    }
+    user.balance -= margin;
+    user.margin_used += margin;
+    post(
+        db,
+        &user,
+        BalanceEntryKind::MarginReserved,
+        -margin,
+        reference_id,
+    )
+}
+
+// Return `margin` to the free balance and book the realized `pnl`
+pub fn settle_position(
+    db: &Store,
+    user_id: &str,
+    margin: f64,
+    pnl: f64,
+    reference_id: &str,
+) -> DomainResult<()> {
+    let _accounts = db.lock_accounts();
+    let mut user = db
+        .get_user(user_id)?
+        .ok_or_else(|| DomainError::not_found("User", user_id))?;
+    user.balance += margin;
+    user.margin_used = (user.margin_used - margin).max(0.0);
+    post(
+        db,
+        &user,
+        BalanceEntryKind::MarginReleased,
+        margin,
+        reference_id,
+    )?;
+    user.balance += pnl;
+    post(db, &user, BalanceEntryKind::RealizedPnl, pnl, reference_id)?;
+    Ok(())
+}
+
+// Credit (positive) or debit (negative) the free balance outside of trading.
+// A debit may not take the balance below zero.
+pub fn adjust_balance(
+    db: &Store,
+    user_id: &str,
+    amount: f64,
+    reference_id: &str,
+) -> DomainResult<BalanceEntry> {
+    let _accounts = db.lock_accounts();
+    let mut user = db
+        .get_user(user_id)?
+        .ok_or_else(|| DomainError::not_found("User", user_id))?;
+    if user.balance + amount < 0.0 {
+        return Err(DomainError::InsufficientFunds {
+            required: -amount,
+            available: user.balance,
+        });
+    }
+    user.balance += amount;
+    post(
+        db,
+        &user,
+        BalanceEntryKind::Adjustment,
+        amount,
+        reference_id,
+    )
+}
+
+// Record the movement together with the updated user
+fn post(
+    db: &Store,
+    user: &User,
+    kind: BalanceEntryKind,
+    amount: f64,
+    reference_id: &str,
+) -> DomainResult<BalanceEntry> {
+    let entry = BalanceEntry {
+        id: ID(Uuid::new_v4().to_string()),
+        user_id: user.id.clone(),
+        kind,
+        amount,
+        balance_after: user.balance,
+        reference_id: ID(reference_id.to_string()),
+        created_at: Utc::now(),
+    };
+    db.record(DomainEvent::BalanceChanged {
+        user: user.clone(),
+        entry: entry.clone(),
+    })?;
+    Ok(entry)
+}
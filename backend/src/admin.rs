@@ -0,0 +1,249 @@
+use crate::accounting;
+use crate::copying::{CopyStatus, StopCopyMode};
+use crate::errors::{DomainError, DomainResult};
+use crate::events::DomainEvent;
+use crate::orders;
+use crate::prices::PricePool;
+use crate::storage::Store;
+use crate::trading;
+use crate::{BalanceEntry, CloseReason, CopyRelation, Trade, User};
+use async_graphql::{Enum, ID, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// ================= Admin Actions =================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum AdminActionKind {
+    SuspendUser,
+    ReinstateUser,
+    ForceCloseTrade,
+    DisableCopyRelation,
+    AdjustBalance,
+}
+
+// Audit record of one admin operation
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+pub struct AdminAction {
+    pub id: ID,
+    // Admin who performed the action
+    pub actor_id: ID,
+    pub kind: AdminActionKind,
+    // User, trade or copy relation acted on
+    pub target_id: ID,
+    pub reason: String,
+    // Balance adjustment amount or forced exit price
+    pub amount: Option<f64>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Block the user from signing in and acting. Open positions stay open.
+pub fn suspend_user(db: &Store, actor_id: &str, user_id: &str, reason: &str) -> DomainResult<User> {
+    let reason = require_reason(reason)?;
+    if actor_id == user_id {
+        return Err(DomainError::conflict("Admins cannot suspend themselves"));
+    }
+    let user = {
+        let _accounts = db.lock_accounts();
+        let mut user = find_user(db, user_id)?;
+        if user.suspended {
+            return Err(DomainError::conflict("User already suspended"));
+        }
+        user.suspended = true;
+        db.record(DomainEvent::UserSuspended { user: user.clone() })?;
+        user
+    };
+    // Resting orders would otherwise still fill for the suspended trader
+    orders::cancel_pending_orders(db, user_id, "Trader suspended")?;
+    record(
+        db,
+        action(
+            actor_id,
+            AdminActionKind::SuspendUser,
+            user_id,
+            reason,
+            None,
+        ),
+    )?;
+    Ok(user)
+}
+
+pub fn reinstate_user(
+    db: &Store,
+    actor_id: &str,
+    user_id: &str,
+    reason: &str,
+) -> DomainResult<User> {
+    let reason = require_reason(reason)?;
+    let user = {
+        let _accounts = db.lock_accounts();
+        let mut user = find_user(db, user_id)?;
+        if !user.suspended {
+            return Err(DomainError::conflict("User is not suspended"));
+        }
+        user.suspended = false;
+        db.record(DomainEvent::UserReinstated { user: user.clone() })?;
+        user
+    };
+    record(
+        db,
+        action(
+            actor_id,
+            AdminActionKind::ReinstateUser,
+            user_id,
+            reason,
+            None,
+        ),
+    )?;
+    Ok(user)
+}
+
+// Close a trade and its copies at `exit_price`, or at the mark price if omitted
+pub fn force_close_trade(
+    db: &Store,
+    prices: &PricePool,
+    actor_id: &str,
+    trade_id: &str,
+    exit_price: Option<f64>,
+    reason: &str,
+) -> DomainResult<Trade> {
+    let reason = require_reason(reason)?;
+    let trade = db
+        .get_trade(trade_id)?
+        .ok_or_else(|| DomainError::not_found("Trade", trade_id))?;
+    let exit_price = match exit_price.or_else(|| prices.price(&trade.symbol)) {
+        Some(price) if price > 0.0 => price,
+        Some(_) => return Err(DomainError::invalid("exitPrice", "Invalid price")),
+        None => {
+            return Err(DomainError::conflict(format!(
+                "No market price for {}",
+                trade.symbol
+            )));
+        }
+    };
+    let trade = trading::close_trade(db, trade_id, exit_price, CloseReason::Admin)?;
+    record(
+        db,
+        action(
+            actor_id,
+            AdminActionKind::ForceCloseTrade,
+            trade_id,
+            reason,
+            Some(exit_price),
+        ),
+    )?;
+    Ok(trade)
+}
+
+// Deactivate a copy relation on the follower's behalf. Open copies stay open
+// until the trader closes, as with stopCopying in KEEP mode.
+pub fn disable_copy_relation(
+    db: &Store,
+    actor_id: &str,
+    relation_id: &str,
+    reason: &str,
+) -> DomainResult<CopyRelation> {
+    let reason = require_reason(reason)?;
+    let relation = {
+        // Accounts for the follower count, relations for the status change
+        let _accounts = db.lock_accounts();
+        let _relations = db.lock_relations();
+        let mut relation = db
+            .get_copy_relation(relation_id)?
+            .ok_or_else(|| DomainError::not_found("CopyRelation", relation_id))?;
+        if relation.status == CopyStatus::Stopped {
+            return Err(DomainError::conflict("Copy relation already stopped"));
+        }
+        relation.status = CopyStatus::Stopped;
+        relation.stop_mode = Some(StopCopyMode::Keep);
+        db.record(DomainEvent::CopyStopped {
+            relation: relation.clone(),
+        })?;
+        if let Some(mut trader) = db.get_user(&relation.trader_id)? {
+            trader.followers_count = (trader.followers_count - 1).max(0);
+            db.record(DomainEvent::FollowerCountChanged { user: trader })?;
+        }
+        relation
+    };
+    record(
+        db,
+        action(
+            actor_id,
+            AdminActionKind::DisableCopyRelation,
+            relation_id,
+            reason,
+            None,
+        ),
+    )?;
+    Ok(relation)
+}
+
+// Credit (positive) or debit (negative) a user's free balance
+pub fn adjust_balance(
+    db: &Store,
+    actor_id: &str,
+    user_id: &str,
+    amount: f64,
+    reason: &str,
+) -> DomainResult<BalanceEntry> {
+    let reason = require_reason(reason)?;
+    if !amount.is_finite() || amount == 0.0 {
+        return Err(DomainError::invalid("amount", "Invalid amount"));
+    }
+    let action = action(
+        actor_id,
+        AdminActionKind::AdjustBalance,
+        user_id,
+        reason,
+        Some(amount),
+    );
+    // The ledger entry points at the audit record
+    let entry = accounting::adjust_balance(db, user_id, amount, &action.id)?;
+    record(db, action)?;
+    Ok(entry)
+}
+
+fn require_reason(reason: &str) -> DomainResult<&str> {
+    match reason.trim() {
+        "" => Err(DomainError::invalid("reason", "Reason is required")),
+        reason => Ok(reason),
+    }
+}
+
+fn find_user(db: &Store, user_id: &str) -> DomainResult<User> {
+    db.get_user(user_id)?
+        .ok_or_else(|| DomainError::not_found("User", user_id))
+}
+
+fn action(
+    actor_id: &str,
+    kind: AdminActionKind,
+    target_id: &str,
+    reason: &str,
+    amount: Option<f64>,
+) -> AdminAction {
+    AdminAction {
+        id: ID(Uuid::new_v4().to_string()),
+        actor_id: ID(actor_id.to_string()),
+        kind,
+        target_id: ID(target_id.to_string()),
+        reason: reason.to_string(),
+        amount,
+        created_at: Utc::now(),
+    }
+}
+
+fn record(db: &Store, action: AdminAction) -> DomainResult<()> {
+    db.record(DomainEvent::AdminActionRecorded {
+        action: action.clone(),
+    })?;
+    println!(
+        "🛡️ Admin {:?} on {} by {}: {}",
+        action.kind,
+        action.target_id.as_str(),
+        action.actor_id.as_str(),
+        action.reason
+    );
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use crate::errors::{DomainError, DomainResult};
+use crate::storage::{DbPool, Store};
+use crate::{Role, User};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{HttpMessage, web};
+use argon2::Argon2;
+use argon2::password_hash::phc::PasswordHash;
+use argon2::password_hash::{PasswordHasher, PasswordVerifier};
+use async_graphql::{Context, ErrorExtensions, Guard, ID, SimpleObject};
+use chrono::Utc;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// ================= Sessions =================
+
+// Authenticated caller, placed in the GraphQL context by `authenticate`
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub user_id: ID,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    iat: i64,
+    exp: i64,
+}
+
+// Signing keys for session tokens (HS256)
+pub struct AuthKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    ttl_hours: i64,
+}
+
+impl AuthKeys {
+    pub fn new(secret: &[u8], ttl_hours: i64) -> Self {
+        AuthKeys {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            ttl_hours,
+        }
+    }
+
+    // Signed session token for `user_id`
+    pub fn issue(&self, user_id: &str) -> DomainResult<String> {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            iat: now,
+            exp: now + self.ttl_hours * 3600,
+        };
+        jsonwebtoken::encode(&Header::default(), &claims, &self.encoding)
+            .map_err(|e| format!("Token error: {}", e).into())
+    }
+
+    // Session for a token with a valid signature that has not expired
+    pub fn verify(&self, token: &str) -> Option<Session> {
+        jsonwebtoken::decode::<Claims>(token, &self.decoding, &Validation::default())
+            .ok()
+            .map(|data| Session {
+                user_id: ID(data.claims.sub),
+            })
+    }
+}
+
+// Keys from JWT_SECRET and JWT_TTL_HOURS (default 24). Without a secret a random
+// one is generated, so sessions do not survive a restart.
+pub fn keys_from_env() -> Result<AuthKeys, String> {
+    let ttl_hours = match std::env::var("JWT_TTL_HOURS") {
+        Ok(raw) => raw
+            .parse()
+            .map_err(|_| format!("Invalid JWT_TTL_HOURS: {}", raw))?,
+        Err(_) => 24,
+    };
+    let secret = match std::env::var("JWT_SECRET") {
+        Ok(secret) => secret,
+        Err(_) => {
+            println!("⚠️ JWT_SECRET not set, sessions end when the server restarts");
+            format!("{}{}", Uuid::new_v4(), Uuid::new_v4())
+        }
+    };
+    Ok(AuthKeys::new(secret.as_bytes(), ttl_hours))
+}
+
+// Actix middleware: attach the Session for a valid `Authorization: Bearer` token.
+// Requests without one pass through; resolvers that need a user reject them.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let token = req
+        .headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let Some(token) = token
+        && let Some(keys) = req.app_data::<web::Data<AuthKeys>>()
+        && let Some(session) = keys.verify(token)
+    {
+        req.extensions_mut().insert(session);
+    }
+    next.call(req).await
+}
+
+// Session for the websocket `connection_init` payload. Browsers cannot set headers
+// on a websocket upgrade, so clients send `{"Authorization": "Bearer <token>"}` here.
+// No token means an anonymous connection; a bad token rejects the connection.
+pub fn connection_session(
+    keys: &AuthKeys,
+    payload: &serde_json::Value,
+) -> DomainResult<Option<Session>> {
+    let header = payload.as_object().and_then(|fields| {
+        fields
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("authorization"))
+            .map(|(_, value)| value)
+    });
+    let Some(header) = header else {
+        return Ok(None);
+    };
+    header
+        .as_str()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| keys.verify(token))
+        .map(Some)
+        .ok_or_else(|| DomainError::unauthenticated("Invalid or expired token"))
+}
+
+// The signed-in user making the request
+pub fn acting_user(ctx: &Context<'_>) -> DomainResult<ID> {
+    Ok(signed_in_user(ctx)?.id)
+}
+
+// The signed-in user's account; suspended accounts are rejected even with a
+// token issued before the suspension
+fn signed_in_user(ctx: &Context<'_>) -> DomainResult<User> {
+    let session = ctx
+        .data_opt::<Session>()
+        .ok_or_else(|| DomainError::unauthenticated("Sign in required"))?;
+    let user = ctx
+        .data_unchecked::<DbPool>()
+        .get_user(&session.user_id)?
+        .ok_or_else(|| DomainError::unauthenticated("Account no longer exists"))?;
+    if user.suspended {
+        return Err(DomainError::forbidden("Account suspended"));
+    }
+    Ok(user)
+}
+
+// Field guard: the signed-in user must hold `role`
+pub struct RoleGuard {
+    role: Role,
+}
+
+impl RoleGuard {
+    pub fn new(role: Role) -> Self {
+        RoleGuard { role }
+    }
+}
+
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        let user = signed_in_user(ctx).map_err(|e| e.extend())?;
+        if !user.has_role(self.role) {
+            return Err(
+                DomainError::forbidden(format!("Requires the {:?} role", self.role)).extend(),
+            );
+        }
+        Ok(())
+    }
+}
+
+// Reject changes to an entity owned by someone other than the acting user
+pub fn ensure_owner(owner: &ID, actor: &ID, entity: &str) -> DomainResult<()> {
+    if owner != actor {
+        return Err(DomainError::forbidden(format!(
+            "{} belongs to another user",
+            entity
+        )));
+    }
+    Ok(())
+}
+
+// ================= Credentials =================
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+pub const MAX_PASSWORD_LEN: usize = 128;
+
+#[derive(SimpleObject)]
+pub struct AuthPayload {
+    pub token: String,
+    pub user: User,
+}
+
+// Argon2id PHC string with a random salt
+pub fn hash_password(password: &str) -> DomainResult<String> {
+    Argon2::default()
+        .hash_password(password.as_bytes())
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Password hash error: {}", e).into())
+}
+
+fn verify_password(hash: &str, password: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|parsed| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok()
+    })
+}
+
+// Exchange a username (any case) and password for a session token
+pub fn login(
+    db: &Store,
+    keys: &AuthKeys,
+    username: &str,
+    password: &str,
+) -> DomainResult<AuthPayload> {
+    let user = db
+        .list_users()?
+        .into_iter()
+        .find(|u| u.username.eq_ignore_ascii_case(username.trim()));
+    // Same answer for unknown users and wrong passwords
+    let Some(user) = user.filter(|u| {
+        u.password_hash
+            .as_deref()
+            .is_some_and(|hash| verify_password(hash, password))
+    }) else {
+        return Err(DomainError::unauthenticated("Invalid username or password"));
+    };
+    if user.suspended {
+        return Err(DomainError::forbidden("Account suspended"));
+    }
+    println!("🔑 Signed in: {}", user.username);
+    Ok(AuthPayload {
+        token: keys.issue(&user.id)?,
+        user,
+    })
+}
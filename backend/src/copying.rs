@@ -0,0 +1,1220 @@
+use crate::auth;
+use crate::errors::{DomainError, DomainResult};
+use crate::events::DomainEvent;
+use crate::prices::PricePool;
+use crate::storage::{StorageResult, Store};
+use crate::trading::AssetClass;
+use crate::{
+    CloseReason, CopiedTrade, CopyRelation, Trade, TradeDirection, TradeStatus, User, accounting,
+    trading,
+};
+use async_graphql::{Enum, ID, InputObject, MaybeUndefined, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value, json};
+use uuid::Uuid;
+
+// ================= Opening Copies =================
+
+// Mirror `trade` for one follower at `entry_price`. A copy that cannot be
+// opened is recorded as skipped rather than failing the trader's trade.
+// Call with the positions lock held, so the relation's limits still hold when
+// the copy is stored.
+pub fn open_copy(
+    db: &Store,
+    relation: &CopyRelation,
+    trade: &Trade,
+    trader: &User,
+    entry_price: f64,
+) -> DomainResult<CopiedTrade> {
+    let follower = db
+        .get_user(&relation.follower_id)?
+        .ok_or_else(|| DomainError::not_found("User", &relation.follower_id))?;
+    let mut copied_trade = new_copy(relation, trade);
+    let skip = match plan_copy(db, relation, trade, &follower, trader, entry_price)? {
+        Ok(quantity) => {
+            copied_trade.entry_price = entry_price;
+            copied_trade.quantity = quantity;
+            copied_trade.margin = accounting::margin_for(entry_price, quantity);
+            copied_trade.stop_loss = relation
+                .stop_loss_pct
+                .map(|pct| trading::level_from_pct(trade.direction, entry_price, pct, true));
+            copied_trade.take_profit = relation
+                .take_profit_pct
+                .map(|pct| trading::level_from_pct(trade.direction, entry_price, pct, false));
+            match accounting::reserve_margin(
+                db,
+                &copied_trade.follower_id,
+                copied_trade.margin,
+                &copied_trade.id,
+            ) {
+                Ok(_) => None,
+                Err(e @ DomainError::InsufficientFunds { .. }) => Some(Skip {
+                    reason: SkipReason::InsufficientFunds,
+                    detail: e.to_string(),
+                }),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(skip) => Some(skip),
+    };
+
+    let Some(skip) = skip else {
+        db.record(DomainEvent::CopiedTradeOpened {
+            copied_trade: copied_trade.clone(),
+        })?;
+        return Ok(copied_trade);
+    };
+    skip_copy(db, relation, copied_trade, skip)
+}
+
+// Mirror an already-open `trade` at its current mark price, e.g. when a
+// follower starts copying. Without a quote the copy is skipped. Call with the
+// positions lock held.
+pub fn open_copy_at_mark(
+    db: &Store,
+    prices: &PricePool,
+    relation: &CopyRelation,
+    trade: &Trade,
+    trader: &User,
+) -> DomainResult<CopiedTrade> {
+    match prices.price(&trade.symbol) {
+        Some(mark) => open_copy(db, relation, trade, trader, mark),
+        None => skip_copy(
+            db,
+            relation,
+            new_copy(relation, trade),
+            Skip {
+                reason: SkipReason::NoMarketPrice,
+                detail: format!("No market price for {}", trade.symbol),
+            },
+        ),
+    }
+}
+
+fn new_copy(relation: &CopyRelation, trade: &Trade) -> CopiedTrade {
+    CopiedTrade {
+        id: ID(Uuid::new_v4().to_string()),
+        original_trade_id: trade.id.clone(),
+        follower_id: relation.follower_id.clone(),
+        relation_id: Some(relation.id.clone()),
+        symbol: trade.symbol.clone(),
+        direction: trade.direction,
+        entry_price: 0.0,
+        exit_price: None,
+        detached: false,
+        quantity: 0.0,
+        margin: 0.0,
+        stop_loss: None,
+        take_profit: None,
+        pnl: None,
+        realized_pnl: 0.0,
+        status: TradeStatus::Open,
+        close_reason: None,
+        skip_reason: None,
+        skip_detail: None,
+        opened_at: Utc::now(),
+        closed_at: None,
+    }
+}
+
+// Record `copied_trade` as skipped, pausing the relation on a drawdown breach
+// (e.g. after its limit was lowered)
+fn skip_copy(
+    db: &Store,
+    relation: &CopyRelation,
+    mut copied_trade: CopiedTrade,
+    skip: Skip,
+) -> DomainResult<CopiedTrade> {
+    println!(
+        "    ⚠️ Skipped {}: {}",
+        copied_trade.follower_id.as_str(),
+        skip.detail
+    );
+    copied_trade.entry_price = 0.0;
+    copied_trade.quantity = 0.0;
+    copied_trade.margin = 0.0;
+    copied_trade.stop_loss = None;
+    copied_trade.take_profit = None;
+    if skip.reason == SkipReason::DrawdownLimit {
+        pause_on_drawdown(db, &relation.id)?;
+    }
+    copied_trade.status = TradeStatus::Skipped;
+    copied_trade.skip_reason = Some(skip.reason);
+    copied_trade.skip_detail = Some(skip.detail);
+    db.record(DomainEvent::CopiedTradeSkipped {
+        copied_trade: copied_trade.clone(),
+    })?;
+    Ok(copied_trade)
+}
+
+// Quantity to copy, or why the relation does not copy this trade: filters
+// first, then sizing, then risk limits
+fn plan_copy(
+    db: &Store,
+    relation: &CopyRelation,
+    trade: &Trade,
+    follower: &User,
+    trader: &User,
+    entry_price: f64,
+) -> DomainResult<Result<f64, Skip>> {
+    if let Some(skip) = relation.filters.check(trade) {
+        return Ok(Err(skip));
+    }
+    let quantity = match copy_quantity(
+        relation,
+        &trade.symbol,
+        trade.quantity,
+        entry_price,
+        follower,
+        trader,
+    ) {
+        Ok(quantity) => quantity,
+        Err(skip) => return Ok(Err(skip)),
+    };
+    let margin = accounting::margin_for(entry_price, quantity);
+    if let Some(skip) = check_limits(db, relation, follower, margin)? {
+        return Ok(Err(skip));
+    }
+    Ok(Ok(quantity))
+}
+
+// Copies logged before they carried their own symbol, direction and fills
+// take them from the original trade, and copies without a relation id get the
+// follower's relation that was live when the trade opened. Only changed copies
+// produce an event.
+pub fn backfill_copies(db: &Store) -> StorageResult<()> {
+    let _positions = db.lock_positions();
+    for mut ct in db.list_copied_trades()? {
+        let missing_fields = ct.symbol.is_empty();
+        if !missing_fields && ct.relation_id.is_some() {
+            continue;
+        }
+        let Some(trade) = db.get_trade(&ct.original_trade_id)? else {
+            continue;
+        };
+        let relation_id = match &ct.relation_id {
+            Some(_) => None,
+            None => db
+                .copy_relations_by_follower(&ct.follower_id)?
+                .into_iter()
+                .filter(|r| r.trader_id == trade.trader_id && r.created_at <= trade.created_at)
+                .max_by_key(|r| r.created_at)
+                .map(|r| r.id),
+        };
+        if !missing_fields && relation_id.is_none() {
+            continue;
+        }
+        if relation_id.is_some() {
+            ct.relation_id = relation_id;
+        }
+        if missing_fields {
+            ct.symbol = trade.symbol.clone();
+            ct.direction = trade.direction;
+            ct.opened_at = trade.created_at;
+            if ct.entry_price == 0.0 && ct.status != TradeStatus::Skipped {
+                ct.entry_price = trade.entry_price;
+            }
+            if ct.status == TradeStatus::Closed {
+                ct.exit_price = ct.exit_price.or(trade.exit_price);
+                ct.closed_at = ct.closed_at.or(trade.closed_at);
+            }
+        }
+        db.record(DomainEvent::CopiedTradeBackfilled { copied_trade: ct })?;
+    }
+    Ok(())
+}
+
+// Copies logged while `entryPrice` was optional hold null when the copy filled
+// at the trader's price; the backfill replaces the zero
+pub fn price_or_null<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(d)?.unwrap_or(0.0))
+}
+
+// ================= Copy Sizing =================
+
+// How a copied trade's quantity is derived from the trader's trade
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum SizingMode {
+    // Trader's quantity times `copyRatio`
+    #[default]
+    FixedRatio,
+    // Trader's quantity scaled by follower equity / trader equity
+    EquityProportional,
+    // `notional` worth of the symbol at the entry price
+    FixedNotional,
+    // Trader's quantity scaled by `allocatedCapital` / trader equity
+    AllocatedCapital,
+}
+
+// Why a trade was not copied to a follower
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum SkipReason {
+    // Sized below the symbol's lot size
+    BelowMinimumSize,
+    // Follower could not cover the margin
+    InsufficientFunds,
+    // Relation already holds `maxOpenPositions` open copies
+    MaxOpenPositions,
+    // Copy notional above `maxTradeNotional`
+    MaxTradeNotional,
+    // Margin in the relation's open copies would exceed `maxAllocation`
+    MaxAllocation,
+    // Relation's losses reached `maxDrawdownPct`; the relation is auto-paused
+    DrawdownLimit,
+    // Symbol not in `includeSymbols`, or in `excludeSymbols`
+    SymbolFilter,
+    // Direction other than the relation's `direction`
+    DirectionFilter,
+    // Asset class not in `assetClasses`
+    AssetClassFilter,
+    // Open position copied on follow, but its symbol has no quote
+    NoMarketPrice,
+}
+
+// A copy that was not opened, with a message for the follower
+#[derive(Debug, Clone)]
+pub struct Skip {
+    pub reason: SkipReason,
+    pub detail: String,
+}
+
+// Balance plus margin held in open positions
+fn equity(user: &User) -> f64 {
+    user.balance + user.margin_used
+}
+
+// Quantity the follower copies, rounded down to the symbol's lot size
+pub fn copy_quantity(
+    relation: &CopyRelation,
+    symbol: &str,
+    quantity: f64,
+    entry_price: f64,
+    follower: &User,
+    trader: &User,
+) -> Result<f64, Skip> {
+    // Share of the trader's equity, so an empty trader account copies nothing
+    let share_of_trader = |capital: f64| {
+        let trader_equity = equity(trader);
+        if trader_equity > 0.0 {
+            quantity * capital / trader_equity
+        } else {
+            0.0
+        }
+    };
+    let sized = match relation.sizing_mode {
+        SizingMode::FixedRatio => quantity * relation.copy_ratio,
+        SizingMode::EquityProportional => share_of_trader(equity(follower)),
+        SizingMode::FixedNotional => relation.notional.unwrap_or(0.0) / entry_price,
+        SizingMode::AllocatedCapital => share_of_trader(relation.allocated_capital.unwrap_or(0.0)),
+    };
+    let lot = trading::lot_size(symbol);
+    let rounded = trading::round_to_lot(sized, lot);
+    if rounded < lot {
+        return Err(Skip {
+            reason: SkipReason::BelowMinimumSize,
+            detail: format!("Size {} is below the lot size {}", sized, lot),
+        });
+    }
+    Ok(rounded)
+}
+
+// ================= Risk Limits =================
+
+// Follower safeguards for one copy relation; unset limits do not apply
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, SimpleObject, InputObject)]
+#[graphql(input_name = "CopyLimitsInput")]
+pub struct CopyLimits {
+    // Most margin held in the relation's open copies at once
+    pub max_allocation: Option<f64>,
+    pub max_open_positions: Option<u32>,
+    // Largest notional (quantity x entry price) of a single copy
+    pub max_trade_notional: Option<f64>,
+    // Realized loss, in percent of `maxAllocation` (or follower equity without
+    // one), at which the relation pauses itself
+    pub max_drawdown_pct: Option<f64>,
+}
+
+impl CopyLimits {
+    pub fn validate(&self) -> DomainResult<()> {
+        let positive = |value: Option<f64>| value.is_none_or(|v| v.is_finite() && v > 0.0);
+        if !positive(self.max_allocation) {
+            return Err(DomainError::invalid(
+                "maxAllocation",
+                "Invalid max allocation",
+            ));
+        }
+        if self.max_open_positions == Some(0) {
+            return Err(DomainError::invalid(
+                "maxOpenPositions",
+                "Invalid max open positions",
+            ));
+        }
+        if !positive(self.max_trade_notional) {
+            return Err(DomainError::invalid(
+                "maxTradeNotional",
+                "Invalid max trade notional",
+            ));
+        }
+        if self
+            .max_drawdown_pct
+            .is_some_and(|pct| !(pct > 0.0 && pct <= 100.0))
+        {
+            return Err(DomainError::invalid(
+                "maxDrawdownPct",
+                "Invalid max drawdown percent",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// First limit a new copy with `margin` would break, if any. Call with the
+// positions lock held so no other copy opens between the check and the insert.
+fn check_limits(
+    db: &Store,
+    relation: &CopyRelation,
+    follower: &User,
+    margin: f64,
+) -> DomainResult<Option<Skip>> {
+    let limits = &relation.limits;
+    if *limits == CopyLimits::default() {
+        return Ok(None);
+    }
+    let skip = |reason, detail| Ok(Some(Skip { reason, detail }));
+    let copies = relation_copies(db, relation)?;
+    let open: Vec<&CopiedTrade> = copies
+        .iter()
+        .filter(|ct| ct.status == TradeStatus::Open)
+        .collect();
+
+    if let Some(detail) = drawdown_breach(relation, follower, &copies) {
+        return skip(SkipReason::DrawdownLimit, detail);
+    }
+    if let Some(max) = limits.max_open_positions
+        && open.len() >= max as usize
+    {
+        return skip(
+            SkipReason::MaxOpenPositions,
+            format!("Already {} open copies (limit {})", open.len(), max),
+        );
+    }
+    if let Some(max) = limits.max_trade_notional
+        && margin > max
+    {
+        return skip(
+            SkipReason::MaxTradeNotional,
+            format!("Notional {:.2} exceeds the {:.2} limit", margin, max),
+        );
+    }
+    if let Some(max) = limits.max_allocation {
+        let allocated: f64 = open.iter().map(|ct| ct.margin).sum();
+        if allocated + margin > max {
+            return skip(
+                SkipReason::MaxAllocation,
+                format!(
+                    "Allocation {:.2} would exceed the {:.2} limit",
+                    allocated + margin,
+                    max
+                ),
+            );
+        }
+    }
+    Ok(None)
+}
+
+// Why the relation's realized losses have reached its drawdown limit, if they have
+fn drawdown_breach(
+    relation: &CopyRelation,
+    follower: &User,
+    copies: &[CopiedTrade],
+) -> Option<String> {
+    let max_pct = relation.limits.max_drawdown_pct?;
+    let loss = relation.drawdown_baseline - realized_pnl(copies);
+    let base = relation
+        .limits
+        .max_allocation
+        .unwrap_or_else(|| equity(follower));
+    let drawdown_pct = if base > 0.0 {
+        loss.max(0.0) / base * 100.0
+    } else {
+        0.0
+    };
+    (drawdown_pct >= max_pct).then(|| {
+        format!(
+            "Drawdown {:.2}% reached the {}% limit",
+            drawdown_pct, max_pct
+        )
+    })
+}
+
+// Closed copies' PnL plus what partial closes booked on open ones
+fn realized_pnl(copies: &[CopiedTrade]) -> f64 {
+    copies
+        .iter()
+        .map(|ct| match ct.status {
+            TradeStatus::Closed => ct.pnl.unwrap_or(0.0),
+            _ => ct.realized_pnl,
+        })
+        .sum()
+}
+
+// Auto-pause the copy's relation if the PnL it just realized took the relation
+// past its drawdown limit, so the pause does not wait for the trader's next trade
+pub fn check_drawdown(db: &Store, ct: &CopiedTrade) -> DomainResult<()> {
+    if let Some(relation) = relation_of(db, ct)? {
+        pause_on_drawdown(db, &relation.id)?;
+    }
+    Ok(())
+}
+
+// Re-read the relation under the relations lock and auto-pause it if it is still
+// active and over its drawdown limit
+fn pause_on_drawdown(db: &Store, relation_id: &ID) -> DomainResult<()> {
+    let _relations = db.lock_relations();
+    let Some(mut relation) = db.get_copy_relation(relation_id)? else {
+        return Ok(());
+    };
+    if relation.status != CopyStatus::Active || relation.limits.max_drawdown_pct.is_none() {
+        return Ok(());
+    }
+    let follower = db
+        .get_user(&relation.follower_id)?
+        .ok_or_else(|| DomainError::not_found("User", &relation.follower_id))?;
+    let copies = relation_copies(db, &relation)?;
+    let Some(detail) = drawdown_breach(&relation, &follower, &copies) else {
+        return Ok(());
+    };
+    change_status(
+        &mut relation,
+        CopyStatus::AutoPaused,
+        CopyChangeKind::AutoPaused,
+    );
+    println!(
+        "    ⏸️ Relation auto-paused: {} ({})",
+        relation.id.as_str(),
+        detail
+    );
+    db.record(DomainEvent::CopyAutoPaused { relation })?;
+    Ok(())
+}
+
+// Why an open copy may not grow by `added_margin` under its relation's
+// notional and allocation limits, if it may not
+pub fn scale_limit(
+    db: &Store,
+    ct: &CopiedTrade,
+    added_margin: f64,
+) -> DomainResult<Option<String>> {
+    let Some(relation) = relation_of(db, ct)? else {
+        return Ok(None);
+    };
+    let limits = &relation.limits;
+    let notional = ct.margin + added_margin;
+    if let Some(max) = limits.max_trade_notional
+        && notional > max
+    {
+        return Ok(Some(format!(
+            "Notional {:.2} would exceed the {:.2} limit",
+            notional, max
+        )));
+    }
+    if let Some(max) = limits.max_allocation {
+        let allocated: f64 = relation_copies(db, &relation)?
+            .iter()
+            .filter(|ct| ct.status == TradeStatus::Open)
+            .map(|ct| ct.margin)
+            .sum();
+        if allocated + added_margin > max {
+            return Ok(Some(format!(
+                "Allocation {:.2} would exceed the {:.2} limit",
+                allocated + added_margin,
+                max
+            )));
+        }
+    }
+    Ok(None)
+}
+
+// Move the stop loss and take profit the relation sets as percentages to the
+// copy's current entry, e.g. after scaling moved its average price
+pub fn reprice_levels(db: &Store, ct: &mut CopiedTrade) -> DomainResult<()> {
+    let Some(relation) = relation_of(db, ct)? else {
+        return Ok(());
+    };
+    if let Some(pct) = relation.stop_loss_pct {
+        ct.stop_loss = Some(trading::level_from_pct(
+            ct.direction,
+            ct.entry_price,
+            pct,
+            true,
+        ));
+    }
+    if let Some(pct) = relation.take_profit_pct {
+        ct.take_profit = Some(trading::level_from_pct(
+            ct.direction,
+            ct.entry_price,
+            pct,
+            false,
+        ));
+    }
+    Ok(())
+}
+
+// Relation a copy belongs to. Copies logged before they carried `relationId`
+// fall back to the follower's current relation with the trader.
+fn relation_of(db: &Store, ct: &CopiedTrade) -> DomainResult<Option<CopyRelation>> {
+    if let Some(id) = &ct.relation_id {
+        return Ok(db.get_copy_relation(id)?);
+    }
+    let Some(trade) = db.get_trade(&ct.original_trade_id)? else {
+        return Ok(None);
+    };
+    Ok(db
+        .copy_relations_by_trader(&trade.trader_id)?
+        .into_iter()
+        .find(|r| r.follower_id == ct.follower_id && r.status.is_following()))
+}
+
+// ================= Instrument Filters =================
+
+// Which of the trader's trades a relation copies; empty lists do not filter
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, SimpleObject, InputObject)]
+#[graphql(input_name = "CopyFiltersInput")]
+pub struct CopyFilters {
+    // Copy only these symbols
+    #[serde(default)]
+    #[graphql(default)]
+    pub include_symbols: Vec<String>,
+    #[serde(default)]
+    #[graphql(default)]
+    pub exclude_symbols: Vec<String>,
+    // Copy only long or only short trades
+    #[serde(default)]
+    pub direction: Option<TradeDirection>,
+    // Copy only these asset classes
+    #[serde(default)]
+    #[graphql(default)]
+    pub asset_classes: Vec<AssetClass>,
+}
+
+impl CopyFilters {
+    pub fn validate(&self) -> DomainResult<()> {
+        if !self
+            .include_symbols
+            .iter()
+            .all(|s| trading::valid_symbol(s))
+        {
+            return Err(DomainError::invalid("includeSymbols", "Invalid symbol"));
+        }
+        if !self
+            .exclude_symbols
+            .iter()
+            .all(|s| trading::valid_symbol(s))
+        {
+            return Err(DomainError::invalid("excludeSymbols", "Invalid symbol"));
+        }
+        Ok(())
+    }
+
+    // Why `trade` is filtered out, if it is
+    fn check(&self, trade: &Trade) -> Option<Skip> {
+        let skip = |reason, detail| Some(Skip { reason, detail });
+        if !self.include_symbols.is_empty() && !self.include_symbols.contains(&trade.symbol) {
+            return skip(
+                SkipReason::SymbolFilter,
+                format!("{} is not an included symbol", trade.symbol),
+            );
+        }
+        if self.exclude_symbols.contains(&trade.symbol) {
+            return skip(
+                SkipReason::SymbolFilter,
+                format!("{} is excluded", trade.symbol),
+            );
+        }
+        if let Some(direction) = self.direction
+            && trade.direction != direction
+        {
+            return skip(
+                SkipReason::DirectionFilter,
+                format!("Only {:?} trades are copied", direction),
+            );
+        }
+        let asset_class = trading::asset_class(&trade.symbol);
+        if !self.asset_classes.is_empty() && !self.asset_classes.contains(&asset_class) {
+            return skip(
+                SkipReason::AssetClassFilter,
+                format!("{:?} trades are not copied", asset_class),
+            );
+        }
+        None
+    }
+}
+
+// Copies made under this relation. Copies without a relation id count if they
+// are of the trader's trades opened since the relation started.
+pub fn relation_copies(db: &Store, relation: &CopyRelation) -> DomainResult<Vec<CopiedTrade>> {
+    let mut copies = Vec::new();
+    for ct in db.copied_trades_by_follower(&relation.follower_id)? {
+        if let Some(relation_id) = &ct.relation_id {
+            if *relation_id == relation.id {
+                copies.push(ct);
+            }
+        } else if let Some(trade) = db.get_trade(&ct.original_trade_id)?
+            && trade.trader_id == relation.trader_id
+            && trade.created_at >= relation.created_at
+        {
+            copies.push(ct);
+        }
+    }
+    Ok(copies)
+}
+
+// ================= Stopping =================
+
+// What happens to a relation's open copies when the follower stops copying
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum StopCopyMode {
+    // Close them at the mark price
+    Close,
+    // Leave them mirroring the trader until the original trades close
+    #[default]
+    Keep,
+    // Unlink them from the trader; the follower closes them with `closeCopiedTrade`
+    Detach,
+}
+
+// The relation's copies that are open and still follow their original trade
+pub fn open_copies(db: &Store, relation: &CopyRelation) -> DomainResult<Vec<CopiedTrade>> {
+    let mut copies = relation_copies(db, relation)?;
+    copies.retain(|ct| ct.status == TradeStatus::Open && !ct.detached);
+    Ok(copies)
+}
+
+// Mark price for each of the relation's open copies; fails if a symbol has no quote
+pub fn close_prices(
+    db: &Store,
+    prices: &PricePool,
+    relation: &CopyRelation,
+) -> DomainResult<Vec<(CopiedTrade, f64)>> {
+    let mut priced = Vec::new();
+    for ct in open_copies(db, relation)? {
+        let mark = prices
+            .price(&ct.symbol)
+            .ok_or_else(|| DomainError::conflict(format!("No market price for {}", ct.symbol)))?;
+        priced.push((ct, mark));
+    }
+    Ok(priced)
+}
+
+// Close the relation's open copies at the mark price; returns how many closed.
+// Copies are read under the positions lock, so one closed concurrently (by its
+// stop loss, say) is skipped rather than failing the rest.
+pub fn close_open_copies(
+    db: &Store,
+    prices: &PricePool,
+    relation: &CopyRelation,
+) -> DomainResult<usize> {
+    let _positions = db.lock_positions();
+    let priced = close_prices(db, prices, relation)?;
+    let count = priced.len();
+    for (ct, mark) in priced {
+        trading::settle_copied_trade(db, ct, mark, CloseReason::StopCopying)?;
+    }
+    Ok(count)
+}
+
+// Unlink the relation's open copies from their original trades; returns how
+// many were detached
+pub fn detach_open_copies(db: &Store, relation: &CopyRelation) -> DomainResult<usize> {
+    let _positions = db.lock_positions();
+    let copies = open_copies(db, relation)?;
+    for mut ct in copies.iter().cloned() {
+        ct.detached = true;
+        db.record(DomainEvent::CopiedTradeDetached { copied_trade: ct })?;
+    }
+    Ok(copies.len())
+}
+
+// Close a detached copy on the follower's behalf at the mark price, the same
+// price stopping with mode CLOSE would have used
+pub fn close_detached_copy(
+    db: &Store,
+    prices: &PricePool,
+    actor_id: &ID,
+    copied_trade_id: &str,
+) -> DomainResult<CopiedTrade> {
+    let ct = db
+        .get_copied_trade(copied_trade_id)?
+        .ok_or_else(|| DomainError::not_found("CopiedTrade", copied_trade_id))?;
+    auth::ensure_owner(&ct.follower_id, actor_id, "Copied trade")?;
+    if !ct.detached {
+        return Err(DomainError::conflict(
+            "Copied trade still follows its original trade",
+        ));
+    }
+    let mark = prices
+        .price(&ct.symbol)
+        .ok_or_else(|| DomainError::conflict(format!("No market price for {}", ct.symbol)))?;
+    trading::close_copied_trade(db, copied_trade_id, mark, CloseReason::Manual)
+}
+
+// ================= Relation Status =================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum CopyStatus {
+    Active,
+    // Paused by the follower; no new trades are copied
+    Paused,
+    // Paused when the drawdown limit was hit
+    AutoPaused,
+    Stopped,
+}
+
+impl CopyStatus {
+    // Paused and auto-paused relations still count as following the trader
+    pub fn is_following(self) -> bool {
+        self != CopyStatus::Stopped
+    }
+}
+
+// Relations logged before `status` existed carry an `active` flag instead
+pub fn status_or_active<'de, D: Deserializer<'de>>(d: D) -> Result<CopyStatus, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Status(CopyStatus),
+        Active(bool),
+    }
+    Ok(match Stored::deserialize(d)? {
+        Stored::Status(status) => status,
+        Stored::Active(true) => CopyStatus::Active,
+        Stored::Active(false) => CopyStatus::Stopped,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum CopyChangeKind {
+    Paused,
+    Resumed,
+    AutoPaused,
+    SettingsUpdated,
+}
+
+// One entry in a relation's history. `before` and `after` hold only what
+// changed, keyed by field name.
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+pub struct CopyRelationChange {
+    pub kind: CopyChangeKind,
+    pub before: Value,
+    pub after: Value,
+    pub changed_at: DateTime<Utc>,
+}
+
+// Settings to change on a relation; omitted fields keep their value and null
+// clears an optional one. `limits` and `filters` are replaced as a whole, and
+// null removes every limit or filter.
+#[derive(Default, InputObject)]
+pub struct UpdateCopySettingsInput {
+    pub copy_ratio: Option<f64>,
+    pub sizing_mode: Option<SizingMode>,
+    pub notional: MaybeUndefined<f64>,
+    pub allocated_capital: MaybeUndefined<f64>,
+    pub stop_loss_pct: MaybeUndefined<f64>,
+    pub take_profit_pct: MaybeUndefined<f64>,
+    pub limits: MaybeUndefined<CopyLimits>,
+    pub filters: MaybeUndefined<CopyFilters>,
+}
+
+// Stop copying new trades until resumed
+pub fn pause(db: &Store, actor_id: &ID, relation_id: &str) -> DomainResult<CopyRelation> {
+    let _relations = db.lock_relations();
+    let mut relation = own_relation(db, actor_id, relation_id)?;
+    match relation.status {
+        CopyStatus::Active => {}
+        CopyStatus::Stopped => return Err(DomainError::conflict("Copy relation already stopped")),
+        CopyStatus::Paused | CopyStatus::AutoPaused => {
+            return Err(DomainError::conflict("Copy relation already paused"));
+        }
+    }
+    change_status(&mut relation, CopyStatus::Paused, CopyChangeKind::Paused);
+    db.record(DomainEvent::CopyPaused {
+        relation: relation.clone(),
+    })?;
+    Ok(relation)
+}
+
+// Copy new trades again after a pause. Resuming an auto-paused relation resets
+// its drawdown: only losses realized after the resume count toward the limit.
+pub fn resume(db: &Store, actor_id: &ID, relation_id: &str) -> DomainResult<CopyRelation> {
+    let _relations = db.lock_relations();
+    let mut relation = own_relation(db, actor_id, relation_id)?;
+    match relation.status {
+        CopyStatus::Paused | CopyStatus::AutoPaused => {}
+        CopyStatus::Stopped => return Err(DomainError::conflict("Copy relation already stopped")),
+        CopyStatus::Active => return Err(DomainError::conflict("Copy relation is not paused")),
+    }
+    if relation.status == CopyStatus::AutoPaused {
+        relation.drawdown_baseline = realized_pnl(&relation_copies(db, &relation)?);
+    }
+    change_status(&mut relation, CopyStatus::Active, CopyChangeKind::Resumed);
+    db.record(DomainEvent::CopyResumed {
+        relation: relation.clone(),
+    })?;
+    Ok(relation)
+}
+
+// Change sizing, risk or filter settings. Copies already open keep their size.
+pub fn update_settings(
+    db: &Store,
+    actor_id: &ID,
+    relation_id: &str,
+    input: UpdateCopySettingsInput,
+) -> DomainResult<CopyRelation> {
+    let _relations = db.lock_relations();
+    let mut relation = own_relation(db, actor_id, relation_id)?;
+    if relation.status == CopyStatus::Stopped {
+        return Err(DomainError::conflict("Copy relation already stopped"));
+    }
+    let before = settings(&relation);
+    if let Some(copy_ratio) = input.copy_ratio {
+        relation.copy_ratio = copy_ratio;
+    }
+    if let Some(sizing_mode) = input.sizing_mode {
+        relation.sizing_mode = sizing_mode;
+    }
+    input.notional.update_to(&mut relation.notional);
+    input
+        .allocated_capital
+        .update_to(&mut relation.allocated_capital);
+    input.stop_loss_pct.update_to(&mut relation.stop_loss_pct);
+    input
+        .take_profit_pct
+        .update_to(&mut relation.take_profit_pct);
+    match input.limits {
+        MaybeUndefined::Value(limits) => relation.limits = limits,
+        MaybeUndefined::Null => relation.limits = CopyLimits::default(),
+        MaybeUndefined::Undefined => {}
+    }
+    match input.filters {
+        MaybeUndefined::Value(filters) => relation.filters = filters,
+        MaybeUndefined::Null => relation.filters = CopyFilters::default(),
+        MaybeUndefined::Undefined => {}
+    }
+    validate_settings(&relation)?;
+
+    let after = settings(&relation);
+    let changed: Vec<&String> = after.keys().filter(|k| before[*k] != after[*k]).collect();
+    if changed.is_empty() {
+        return Ok(relation);
+    }
+    let pick = |values: &Map<String, Value>| -> Value {
+        changed
+            .iter()
+            .map(|k| ((*k).clone(), values[*k].clone()))
+            .collect::<Map<_, _>>()
+            .into()
+    };
+    relation.settings_history.push(CopyRelationChange {
+        kind: CopyChangeKind::SettingsUpdated,
+        before: pick(&before),
+        after: pick(&after),
+        changed_at: Utc::now(),
+    });
+    db.record(DomainEvent::CopySettingsUpdated {
+        relation: relation.clone(),
+    })?;
+    Ok(relation)
+}
+
+// Sizing, stop/take-profit, limits and filters of a new or updated relation
+pub fn validate_settings(relation: &CopyRelation) -> DomainResult<()> {
+    let valid_pct = |pct: Option<f64>| pct.is_none_or(|p| p > 0.0 && p < 100.0);
+    let positive = |amount: Option<f64>| amount.is_some_and(|a| a.is_finite() && a > 0.0);
+    match relation.sizing_mode {
+        SizingMode::FixedRatio if relation.copy_ratio < 0.01 || relation.copy_ratio > 1.0 => {
+            return Err(DomainError::invalid("copyRatio", "Invalid copy ratio"));
+        }
+        SizingMode::FixedNotional if !positive(relation.notional) => {
+            return Err(DomainError::invalid("notional", "Invalid notional"));
+        }
+        SizingMode::AllocatedCapital if !positive(relation.allocated_capital) => {
+            return Err(DomainError::invalid(
+                "allocatedCapital",
+                "Invalid allocated capital",
+            ));
+        }
+        _ => {}
+    }
+    if !valid_pct(relation.stop_loss_pct) {
+        return Err(DomainError::invalid(
+            "stopLossPct",
+            "Invalid stop loss percent",
+        ));
+    }
+    if !valid_pct(relation.take_profit_pct) {
+        return Err(DomainError::invalid(
+            "takeProfitPct",
+            "Invalid take profit percent",
+        ));
+    }
+    relation.limits.validate()?;
+    relation.filters.validate()
+}
+
+fn own_relation(db: &Store, actor_id: &ID, relation_id: &str) -> DomainResult<CopyRelation> {
+    let relation = db
+        .get_copy_relation(relation_id)?
+        .ok_or_else(|| DomainError::not_found("CopyRelation", relation_id))?;
+    auth::ensure_owner(&relation.follower_id, actor_id, "Copy relation")?;
+    Ok(relation)
+}
+
+fn change_status(relation: &mut CopyRelation, status: CopyStatus, kind: CopyChangeKind) {
+    relation.settings_history.push(CopyRelationChange {
+        kind,
+        before: json!({ "status": relation.status }),
+        after: json!({ "status": status }),
+        changed_at: Utc::now(),
+    });
+    relation.status = status;
+}
+
+fn settings(relation: &CopyRelation) -> Map<String, Value> {
+    let settings = json!({
+        "copyRatio": relation.copy_ratio,
+        "sizingMode": relation.sizing_mode,
+        "notional": relation.notional,
+        "allocatedCapital": relation.allocated_capital,
+        "stopLossPct": relation.stop_loss_pct,
+        "takeProfitPct": relation.take_profit_pct,
+        "limits": relation.limits,
+        "filters": relation.filters,
+    });
+    match settings {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryRepository;
+
+    // Active relation "r" from follower "f" to trader "t" with the given
+    // settings on top of the stored defaults
+    fn relation(settings: Value) -> CopyRelation {
+        let mut stored = json!({
+            "id": "r", "follower_id": "f", "trader_id": "t", "copy_ratio": 1.0,
+            "status": "Active", "created_at": Utc::now(),
+        });
+        stored
+            .as_object_mut()
+            .unwrap()
+            .extend(settings.as_object().unwrap().clone());
+        serde_json::from_value(stored).unwrap()
+    }
+
+    fn user(id: &str, balance: f64, margin_used: f64) -> User {
+        let mut user = User::new(id, id, balance, id == "t");
+        user.margin_used = margin_used;
+        user
+    }
+
+    fn quantity(relation: &CopyRelation, symbol: &str, quantity: f64) -> Result<f64, SkipReason> {
+        let follower = user("f", 2_000.0, 0.0);
+        let trader = user("t", 8_000.0, 2_000.0);
+        copy_quantity(relation, symbol, quantity, 100.0, &follower, &trader).map_err(|s| s.reason)
+    }
+
+    #[test]
+    fn fixed_ratio_scales_the_trader_quantity() {
+        let relation = relation(json!({ "copy_ratio": 0.3 }));
+        assert_eq!(quantity(&relation, "SOL/USD", 1.0), Ok(0.3));
+        // 0.3 x 0.05 = 0.015, rounded down to the 0.01 SOL lot
+        assert_eq!(quantity(&relation, "SOL/USD", 0.05), Ok(0.01));
+    }
+
+    #[test]
+    fn equity_proportional_uses_follower_over_trader_equity() {
+        let relation = relation(json!({ "sizing_mode": "EquityProportional" }));
+        // Follower equity 2000 over trader equity 10000 (balance plus margin)
+        assert_eq!(quantity(&relation, "BTC/USD", 1.0), Ok(0.2));
+    }
+
+    #[test]
+    fn fixed_notional_buys_the_notional_at_the_entry() {
+        let relation = relation(json!({ "sizing_mode": "FixedNotional", "notional": 250.0 }));
+        assert_eq!(quantity(&relation, "ETH/USD", 10.0), Ok(2.5));
+    }
+
+    #[test]
+    fn allocated_capital_uses_allocation_over_trader_equity() {
+        let relation = relation(json!({
+            "sizing_mode": "AllocatedCapital", "allocated_capital": 500.0,
+        }));
+        assert_eq!(quantity(&relation, "SOL/USD", 4.0), Ok(0.2));
+    }
+
+    #[test]
+    fn sizes_below_a_lot_are_skipped() {
+        let relation = relation(json!({ "copy_ratio": 0.01 }));
+        assert_eq!(
+            quantity(&relation, "SOL/USD", 0.5),
+            Err(SkipReason::BelowMinimumSize)
+        );
+    }
+
+    #[test]
+    fn an_empty_trader_account_copies_nothing() {
+        let relation = relation(json!({ "sizing_mode": "EquityProportional" }));
+        let follower = user("f", 2_000.0, 0.0);
+        let trader = user("t", 0.0, 0.0);
+        let sized = copy_quantity(&relation, "SOL/USD", 1.0, 100.0, &follower, &trader);
+        assert_eq!(sized.unwrap_err().reason, SkipReason::BelowMinimumSize);
+    }
+
+    // Copy under relation "r" with `margin` held, booked `pnl` once closed
+    fn stored_copy(id: &str, status: &str, margin: f64, pnl: Option<f64>) -> CopiedTrade {
+        serde_json::from_value(json!({
+            "id": id, "original_trade_id": "trade", "follower_id": "f", "relation_id": "r",
+            "symbol": "SOL/USD", "direction": "Long", "entry_price": 100.0,
+            "quantity": margin / 100.0, "margin": margin, "pnl": pnl, "status": status,
+        }))
+        .unwrap()
+    }
+
+    fn limits_hit(
+        relation: &CopyRelation,
+        copies: &[CopiedTrade],
+        margin: f64,
+    ) -> Option<SkipReason> {
+        let db = Store::new(Box::new(MemoryRepository::default()));
+        for ct in copies {
+            db.save_copied_trade(ct).unwrap();
+        }
+        let follower = user("f", 1_000.0, 0.0);
+        check_limits(&db, relation, &follower, margin)
+            .unwrap()
+            .map(|skip| skip.reason)
+    }
+
+    #[test]
+    fn no_limits_copy_anything() {
+        let copies = [stored_copy("a", "Open", 1e6, None)];
+        assert_eq!(limits_hit(&relation(json!({})), &copies, 1e6), None);
+    }
+
+    #[test]
+    fn max_open_positions_counts_open_copies_only() {
+        let relation = relation(json!({ "limits": { "max_open_positions": 2 } }));
+        let copies = [
+            stored_copy("a", "Open", 10.0, None),
+            stored_copy("b", "Closed", 10.0, Some(1.0)),
+            stored_copy("c", "Skipped", 0.0, None),
+        ];
+        assert_eq!(limits_hit(&relation, &copies, 10.0), None);
+        let copies = [
+            stored_copy("a", "Open", 10.0, None),
+            stored_copy("b", "Open", 10.0, None),
+        ];
+        assert_eq!(
+            limits_hit(&relation, &copies, 10.0),
+            Some(SkipReason::MaxOpenPositions)
+        );
+    }
+
+    #[test]
+    fn max_trade_notional_caps_a_single_copy() {
+        let relation = relation(json!({ "limits": { "max_trade_notional": 100.0 } }));
+        assert_eq!(limits_hit(&relation, &[], 100.0), None);
+        assert_eq!(
+            limits_hit(&relation, &[], 100.01),
+            Some(SkipReason::MaxTradeNotional)
+        );
+    }
+
+    #[test]
+    fn max_allocation_adds_the_new_margin_to_open_copies() {
+        let relation = relation(json!({ "limits": { "max_allocation": 100.0 } }));
+        let copies = [
+            stored_copy("a", "Open", 60.0, None),
+            stored_copy("b", "Closed", 500.0, Some(0.0)),
+        ];
+        assert_eq!(limits_hit(&relation, &copies, 40.0), None);
+        assert_eq!(
+            limits_hit(&relation, &copies, 40.5),
+            Some(SkipReason::MaxAllocation)
+        );
+    }
+
+    #[test]
+    fn drawdown_is_measured_against_the_allocation() {
+        let relation = relation(json!({
+            "limits": { "max_allocation": 200.0, "max_drawdown_pct": 10.0 },
+        }));
+        let follower = user("f", 1_000.0, 0.0);
+        // 19 lost out of 200 is 9.5%
+        let copies = [
+            stored_copy("a", "Closed", 10.0, Some(-25.0)),
+            stored_copy("b", "Closed", 10.0, Some(6.0)),
+        ];
+        assert_eq!(drawdown_breach(&relation, &follower, &copies), None);
+        // Partial closes on open copies count too: 21 lost is 10.5%
+        let mut open = stored_copy("c", "Open", 10.0, None);
+        open.realized_pnl = -2.0;
+        let copies = [copies[0].clone(), copies[1].clone(), open];
+        assert!(drawdown_breach(&relation, &follower, &copies).is_some());
+        assert_eq!(
+            limits_hit(&relation, &copies, 1.0),
+            Some(SkipReason::DrawdownLimit)
+        );
+    }
+
+    #[test]
+    fn drawdown_falls_back_to_follower_equity_and_counts_from_the_baseline() {
+        let mut relation = relation(json!({ "limits": { "max_drawdown_pct": 5.0 } }));
+        // Balance 1000 plus 100 in margin is 1100 of equity; 55 lost is 5%
+        let follower = user("f", 1_000.0, 100.0);
+        let copies = [stored_copy("a", "Closed", 10.0, Some(-55.0))];
+        assert!(drawdown_breach(&relation, &follower, &copies).is_some());
+        // Resumed after an auto-pause at -50: only the 5 lost since counts
+        relation.drawdown_baseline = -50.0;
+        assert_eq!(drawdown_breach(&relation, &follower, &copies), None);
+    }
+
+    #[test]
+    fn relations_read_their_status() {
+        for (status, expected) in [
+            ("Active", CopyStatus::Active),
+            ("Paused", CopyStatus::Paused),
+            ("AutoPaused", CopyStatus::AutoPaused),
+            ("Stopped", CopyStatus::Stopped),
+        ] {
+            assert_eq!(relation(json!({ "status": status })).status, expected);
+        }
+    }
+
+    #[test]
+    fn older_relations_map_their_active_flag() {
+        let stored = |active: bool| {
+            serde_json::from_value::<CopyRelation>(json!({
+                "id": "r", "follower_id": "f", "trader_id": "t", "copy_ratio": 1.0,
+                "active": active, "created_at": Utc::now(),
+            }))
+            .unwrap()
+        };
+        assert_eq!(stored(true).status, CopyStatus::Active);
+        assert_eq!(stored(false).status, CopyStatus::Stopped);
+    }
+
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        let stored = json!({
+            "id": "r", "follower_id": "f", "trader_id": "t", "copy_ratio": 1.0,
+            "status": "Sleeping", "created_at": Utc::now(),
+        });
+        assert!(serde_json::from_value::<CopyRelation>(stored).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use async_graphql::{ErrorExtensions, Value};
+use std::fmt;
+
+// ================= Domain Errors =================
+
+// Failures surfaced to API clients. Each variant maps to a stable `code` in the
+// GraphQL error extensions so clients can branch without parsing messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomainError {
+    // Input rejected before anything changed
+    ValidationFailed {
+        field: String,
+        message: String,
+    },
+    NotFound {
+        entity: String,
+        id: String,
+    },
+    InsufficientFunds {
+        required: f64,
+        available: f64,
+    },
+    // A BPMN process could not finish; `node` is the last task or gateway reached
+    WorkflowFailed {
+        process: String,
+        node: Option<String>,
+        message: String,
+    },
+    // The entity is not in a state that allows the change
+    Conflict {
+        message: String,
+    },
+    // No valid session token, or wrong credentials
+    Unauthenticated {
+        message: String,
+    },
+    // Signed in, but acting on someone else's data
+    Forbidden {
+        message: String,
+    },
+    // Storage or other unexpected failure
+    Internal {
+        message: String,
+    },
+}
+
+pub type DomainResult<T> = Result<T, DomainError>;
+
+impl DomainError {
+    pub fn invalid(field: &str, message: &str) -> Self {
+        DomainError::ValidationFailed {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn not_found(entity: &str, id: &str) -> Self {
+        DomainError::NotFound {
+            entity: entity.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        DomainError::Conflict {
+            message: message.into(),
+        }
+    }
+
+    pub fn unauthenticated(message: impl Into<String>) -> Self {
+        DomainError::Unauthenticated {
+            message: message.into(),
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        DomainError::Forbidden {
+            message: message.into(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            DomainError::ValidationFailed { .. } => "VALIDATION_FAILED",
+            DomainError::NotFound { .. } => "NOT_FOUND",
+            DomainError::InsufficientFunds { .. } => "INSUFFICIENT_FUNDS",
+            DomainError::WorkflowFailed { .. } => "WORKFLOW_FAILED",
+            DomainError::Conflict { .. } => "CONFLICT",
+            DomainError::Unauthenticated { .. } => "UNAUTHENTICATED",
+            DomainError::Forbidden { .. } => "FORBIDDEN",
+            DomainError::Internal { .. } => "INTERNAL",
+        }
+    }
+}
+
+impl fmt::Display for DomainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomainError::ValidationFailed { message, .. }
+            | DomainError::WorkflowFailed { message, .. }
+            | DomainError::Conflict { message }
+            | DomainError::Unauthenticated { message }
+            | DomainError::Forbidden { message }
+            | DomainError::Internal { message } => f.write_str(message),
+            DomainError::NotFound { entity, id } => write!(f, "{} not found: {}", entity, id),
+            DomainError::InsufficientFunds {
+                required,
+                available,
+            } => write!(
+                f,
+                "Insufficient funds: balance {:.2}, required {:.2}",
+                available, required
+            ),
+        }
+    }
+}
+
+// Storage reports failures as plain strings
+impl From<String> for DomainError {
+    fn from(message: String) -> Self {
+        DomainError::Internal { message }
+    }
+}
+
+impl ErrorExtensions for DomainError {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.to_string()).extend_with(|_, ext| {
+            ext.set("code", self.code());
+            match self {
+                DomainError::ValidationFailed { field, .. } => ext.set("field", field.as_str()),
+                DomainError::NotFound { entity, id } => {
+                    ext.set("entity", entity.as_str());
+                    ext.set("id", id.as_str());
+                }
+                DomainError::InsufficientFunds {
+                    required,
+                    available,
+                } => {
+                    ext.set("required", *required);
+                    ext.set("available", *available);
+                }
+                DomainError::WorkflowFailed { process, node, .. } => {
+                    ext.set("process", process.as_str());
+                    ext.set("node", node.as_deref().map_or(Value::Null, Value::from));
+                }
+                DomainError::Conflict { .. }
+                | DomainError::Unauthenticated { .. }
+                | DomainError::Forbidden { .. }
+                | DomainError::Internal { .. } => {}
+            }
+        })
+    }
+}
@@ -0,0 +1,537 @@
+use crate::admin::AdminAction;
+use crate::orders::Order;
+use crate::storage::{Repository, StorageResult};
+use crate::workflows::WorkflowInstance;
+use crate::{BalanceEntry, CopiedTrade, CopyRelation, Trade, User};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::broadcast;
+
+// ================= Domain Events =================
+
+// Every state change is expressed as one of these events. Each event carries
+// the resulting entity so applying it is a plain upsert and replay is idempotent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DomainEvent {
+    UserRegistered { user: User },
+    FollowerCountChanged { user: User },
+    StatsUpdated { user: User },
+    UserSuspended { user: User },
+    UserReinstated { user: User },
+    TradeOpened { trade: Trade },
+    TradeClosed { trade: Trade },
+    TradeReduced { trade: Trade },
+    TradeIncreased { trade: Trade },
+    CopyStarted { relation: CopyRelation },
+    CopyStopped { relation: CopyRelation },
+    CopyAutoPaused { relation: CopyRelation },
+    CopyPaused { relation: CopyRelation },
+    CopyResumed { relation: CopyRelation },
+    CopySettingsUpdated { relation: CopyRelation },
+    CopiedTradeOpened { copied_trade: CopiedTrade },
+    CopiedTradeClosed { copied_trade: CopiedTrade },
+    CopiedTradeReduced { copied_trade: CopiedTrade },
+    CopiedTradeIncreased { copied_trade: CopiedTrade },
+    CopiedTradeSkipped { copied_trade: CopiedTrade },
+    CopiedTradeDetached { copied_trade: CopiedTrade },
+    CopiedTradeBackfilled { copied_trade: CopiedTrade },
+    BalanceChanged { user: User, entry: BalanceEntry },
+    OrderPlaced { order: Order },
+    OrderAmended { order: Order },
+    OrderFilled { order: Order },
+    OrderCancelled { order: Order },
+    OrderExpired { order: Order },
+    WorkflowCompleted { instance: WorkflowInstance },
+    AdminActionRecorded { action: AdminAction },
+}
+
+impl DomainEvent {
+    // Apply the event to a repository
+    pub fn apply(&self, repo: &dyn Repository) -> StorageResult<()> {
+        match self {
+            DomainEvent::UserRegistered { user }
+            | DomainEvent::FollowerCountChanged { user }
+            | DomainEvent::StatsUpdated { user }
+            | DomainEvent::UserSuspended { user }
+            | DomainEvent::UserReinstated { user } => repo.save_user(user),
+            DomainEvent::TradeOpened { trade }
+            | DomainEvent::TradeClosed { trade }
+            | DomainEvent::TradeReduced { trade }
+            | DomainEvent::TradeIncreased { trade } => repo.save_trade(trade),
+            DomainEvent::CopyStarted { relation }
+            | DomainEvent::CopyStopped { relation }
+            | DomainEvent::CopyAutoPaused { relation }
+            | DomainEvent::CopyPaused { relation }
+            | DomainEvent::CopyResumed { relation }
+            | DomainEvent::CopySettingsUpdated { relation } => repo.save_copy_relation(relation),
+            DomainEvent::CopiedTradeOpened { copied_trade }
+            | DomainEvent::CopiedTradeClosed { copied_trade }
+            | DomainEvent::CopiedTradeReduced { copied_trade }
+            | DomainEvent::CopiedTradeIncreased { copied_trade }
+            | DomainEvent::CopiedTradeSkipped { copied_trade }
+            | DomainEvent::CopiedTradeDetached { copied_trade }
+            | DomainEvent::CopiedTradeBackfilled { copied_trade } => {
+                repo.save_copied_trade(copied_trade)
+            }
+            DomainEvent::BalanceChanged { user, entry } => {
+                repo.save_balance_entry(entry)?;
+                repo.save_user(user)
+            }
+            DomainEvent::OrderPlaced { order }
+            | DomainEvent::OrderAmended { order }
+            | DomainEvent::OrderFilled { order }
+            | DomainEvent::OrderCancelled { order }
+            | DomainEvent::OrderExpired { order } => repo.save_order(order),
+            DomainEvent::WorkflowCompleted { instance } => repo.save_workflow_instance(instance),
+            DomainEvent::AdminActionRecorded { action } => repo.save_admin_action(action),
+        }
+    }
+}
+
+// One line of the event log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub seq: u64,
+    pub recorded_at: DateTime<Utc>,
+    pub event: DomainEvent,
+}
+
+// Full repository state at a given sequence number
+#[derive(Default, Serialize, Deserialize)]
+struct Snapshot {
+    seq: u64,
+    users: Vec<User>,
+    trades: Vec<Trade>,
+    copy_relations: Vec<CopyRelation>,
+    copied_trades: Vec<CopiedTrade>,
+    #[serde(default)]
+    balance_entries: Vec<BalanceEntry>,
+    #[serde(default)]
+    orders: Vec<Order>,
+    #[serde(default)]
+    workflow_instances: Vec<WorkflowInstance>,
+    #[serde(default)]
+    admin_actions: Vec<AdminAction>,
+}
+
+// ================= Event Log =================
+
+// Append-only JSON-lines log (events.jsonl) plus a periodic snapshot
+// (snapshot.json) stored in one directory. Each snapshot closes the current
+// segment: events.jsonl is archived as events-<seq>.jsonl and a new one started,
+// so a boot only reads the events since the last snapshot.
+pub struct EventLog {
+    file: File,
+    dir: PathBuf,
+    log_path: PathBuf,
+    snapshot_path: PathBuf,
+    snapshot_interval: u64,
+    last_seq: u64,
+    last_snapshot_seq: u64,
+    // Length of the current segment before the last append, until it is
+    // retracted or a snapshot closes the segment
+    last_append_at: Option<u64>,
+}
+
+impl EventLog {
+    // Open the log in `dir` and rebuild `repo` from the latest snapshot plus
+    // every event recorded after it
+    pub fn open_and_replay(
+        dir: &Path,
+        snapshot_interval: u64,
+        repo: &dyn Repository,
+    ) -> StorageResult<Self> {
+        fs::create_dir_all(dir).map_err(|e| format!("Event log error: {}", e))?;
+        let log_path = dir.join("events.jsonl");
+        let snapshot_path = dir.join("snapshot.json");
+
+        let snapshot = read_snapshot(&snapshot_path)?;
+        let snapshot_seq = snapshot.seq;
+        for user in &snapshot.users {
+            repo.save_user(user)?;
+        }
+        for trade in &snapshot.trades {
+            repo.save_trade(trade)?;
+        }
+        for relation in &snapshot.copy_relations {
+            repo.save_copy_relation(relation)?;
+        }
+        for copied_trade in &snapshot.copied_trades {
+            repo.save_copied_trade(copied_trade)?;
+        }
+        for entry in &snapshot.balance_entries {
+            repo.save_balance_entry(entry)?;
+        }
+        for order in &snapshot.orders {
+            repo.save_order(order)?;
+        }
+        for instance in &snapshot.workflow_instances {
+            repo.save_workflow_instance(instance)?;
+        }
+        for action in &snapshot.admin_actions {
+            repo.save_admin_action(action)?;
+        }
+
+        let mut last_seq = snapshot_seq;
+        let mut replayed = 0;
+        if log_path.exists() {
+            let mut reader = BufReader::new(
+                File::open(&log_path).map_err(|e| format!("Event log error: {}", e))?,
+            );
+            let mut line = String::new();
+            let mut line_no = 0;
+            // End of the last complete line
+            let mut complete_len = 0;
+            loop {
+                line.clear();
+                let read = reader
+                    .read_line(&mut line)
+                    .map_err(|e| format!("Event log error: {}", e))?;
+                if read == 0 {
+                    break;
+                }
+                line_no += 1;
+                // Appends end in a newline, so a line without one was cut off by a
+                // crash before it was acknowledged; drop it
+                if !line.ends_with('\n') {
+                    println!("⚠️ Event log: dropping unfinished line {}", line_no);
+                    truncate(&log_path, complete_len)?;
+                    break;
+                }
+                complete_len += read as u64;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let corrupt = |e| format!("Event log line {} is corrupt: {}", line_no, e);
+                // Events the snapshot already holds need only their sequence number
+                let SeqOnly { seq } = serde_json::from_str(&line).map_err(corrupt)?;
+                if seq <= snapshot_seq {
+                    continue;
+                }
+                let record: EventRecord = serde_json::from_str(&line).map_err(corrupt)?;
+                record.event.apply(repo)?;
+                last_seq = record.seq;
+                replayed += 1;
+            }
+        }
+        println!(
+            "📜 Event log: snapshot @{} + {} replayed events",
+            snapshot_seq, replayed
+        );
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .map_err(|e| format!("Event log error: {}", e))?;
+        Ok(EventLog {
+            file,
+            dir: dir.to_path_buf(),
+            log_path,
+            snapshot_path,
+            snapshot_interval,
+            last_seq,
+            last_snapshot_seq: snapshot_seq,
+            last_append_at: None,
+        })
+    }
+
+    // Durably append an event and return its record
+    pub fn append(&mut self, event: DomainEvent) -> StorageResult<EventRecord> {
+        let record = EventRecord {
+            seq: self.last_seq + 1,
+            recorded_at: Utc::now(),
+            event,
+        };
+        let mut line =
+            serde_json::to_string(&record).map_err(|e| format!("Event encode error: {}", e))?;
+        line.push('\n');
+        let start = self
+            .file
+            .metadata()
+            .map_err(|e| format!("Event log write error: {}", e))?
+            .len();
+        if let Err(e) = self
+            .file
+            .write_all(line.as_bytes())
+            .and_then(|_| self.file.sync_data())
+        {
+            // Drop whatever part of the line made it to disk
+            truncate(&self.log_path, start)?;
+            return Err(format!("Event log write error: {}", e));
+        }
+        self.last_seq = record.seq;
+        self.last_append_at = Some(start);
+        Ok(record)
+    }
+
+    // Take back the event just appended as `seq` after it could not be applied,
+    // so a replay never commits an event its caller saw fail
+    pub fn retract(&mut self, seq: u64) -> StorageResult<()> {
+        let Some(len) = self.last_append_at.filter(|_| seq == self.last_seq) else {
+            return Err(format!("Event {} is not the last one appended", seq));
+        };
+        truncate(&self.log_path, len)?;
+        self.last_append_at = None;
+        self.last_seq -= 1;
+        Ok(())
+    }
+
+    // Write a snapshot once enough events have accumulated since the last one,
+    // then start a new log segment
+    pub fn maybe_snapshot(&mut self, repo: &dyn Repository) -> StorageResult<()> {
+        if self.snapshot_interval == 0
+            || self.last_seq - self.last_snapshot_seq < self.snapshot_interval
+        {
+            return Ok(());
+        }
+        let snapshot = Snapshot {
+            seq: self.last_seq,
+            users: repo.list_users()?,
+            trades: repo.list_trades()?,
+            copy_relations: repo.list_copy_relations()?,
+            copied_trades: repo.list_copied_trades()?,
+            balance_entries: repo.list_balance_entries()?,
+            orders: repo.list_orders()?,
+            workflow_instances: repo.list_workflow_instances()?,
+            admin_actions: repo.list_admin_actions()?,
+        };
+        let data =
+            serde_json::to_vec(&snapshot).map_err(|e| format!("Snapshot encode error: {}", e))?;
+        // Write to a temp file first so a crash never leaves a half-written snapshot
+        let tmp_path = self.snapshot_path.with_extension("json.tmp");
+        File::create(&tmp_path)
+            .and_then(|mut tmp| tmp.write_all(&data).and_then(|_| tmp.sync_all()))
+            .and_then(|_| fs::rename(&tmp_path, &self.snapshot_path))
+            .and_then(|_| File::open(&self.dir)?.sync_all())
+            .map_err(|e| format!("Snapshot write error: {}", e))?;
+        self.last_snapshot_seq = self.last_seq;
+        self.last_append_at = None;
+        println!("📸 Snapshot written @{}", self.last_seq);
+
+        // The snapshot is durable, so the events before it are only history now.
+        // A crash before the rename just leaves them to be skipped on replay.
+        let segment_path = self.dir.join(format!("events-{:012}.jsonl", self.last_seq));
+        fs::rename(&self.log_path, &segment_path)
+            .map_err(|e| format!("Event log rotate error: {}", e))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+            .map_err(|e| format!("Event log error: {}", e))?;
+        Ok(())
+    }
+}
+
+// Sequence number of a log line, read without decoding the event
+#[derive(Deserialize)]
+struct SeqOnly {
+    seq: u64,
+}
+
+fn truncate(path: &Path, len: u64) -> StorageResult<()> {
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_len(len).and_then(|_| file.sync_all()))
+        .map_err(|e| format!("Event log error: {}", e))
+}
+
+fn read_snapshot(path: &Path) -> StorageResult<Snapshot> {
+    if !path.exists() {
+        return Ok(Snapshot::default());
+    }
+    let data = fs::read(path).map_err(|e| format!("Snapshot read error: {}", e))?;
+    serde_json::from_slice(&data).map_err(|e| format!("Snapshot is corrupt: {}", e))
+}
+
+// ================= Event Bus =================
+
+// In-process fan-out of recorded events to live subscribers (GraphQL subscriptions)
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<DomainEvent>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        EventBus { sender }
+    }
+
+    // Publishing with no subscribers is not an error
+    pub fn publish(&self, event: DomainEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DomainEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryRepository;
+
+    // Fresh directory under the system temp dir, removed on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("event-log-{}", uuid::Uuid::new_v4()));
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn registered(id: &str) -> DomainEvent {
+        DomainEvent::UserRegistered {
+            user: User::new(id, id, 1_000.0, false),
+        }
+    }
+
+    // Append the events the way Store::record does: log, apply, maybe snapshot
+    fn record(log: &mut EventLog, repo: &MemoryRepository, ids: &[&str]) {
+        for id in ids {
+            let record = log.append(registered(id)).unwrap();
+            record.event.apply(repo).unwrap();
+            log.maybe_snapshot(repo).unwrap();
+        }
+    }
+
+    fn usernames(repo: &MemoryRepository) -> Vec<String> {
+        let mut names: Vec<String> = repo
+            .list_users()
+            .unwrap()
+            .into_iter()
+            .map(|u| u.username)
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn lines(path: &Path) -> usize {
+        fs::read_to_string(path).unwrap().lines().count()
+    }
+
+    #[test]
+    fn replay_rebuilds_the_repository() {
+        let dir = TempDir::new();
+        let repo = MemoryRepository::default();
+        let mut log = EventLog::open_and_replay(&dir.0, 0, &repo).unwrap();
+        record(&mut log, &repo, &["a", "b"]);
+        drop(log);
+
+        let replayed = MemoryRepository::default();
+        let mut log = EventLog::open_and_replay(&dir.0, 0, &replayed).unwrap();
+        assert_eq!(usernames(&replayed), ["a", "b"]);
+        assert_eq!(log.append(registered("c")).unwrap().seq, 3);
+    }
+
+    #[test]
+    fn an_unfinished_last_line_is_dropped() {
+        let dir = TempDir::new();
+        let repo = MemoryRepository::default();
+        let mut log = EventLog::open_and_replay(&dir.0, 0, &repo).unwrap();
+        record(&mut log, &repo, &["a", "b"]);
+        drop(log);
+        let log_path = dir.0.join("events.jsonl");
+        let complete = fs::metadata(&log_path).unwrap().len();
+        // A crash in the middle of the third append
+        OpenOptions::new()
+            .append(true)
+            .open(&log_path)
+            .unwrap()
+            .write_all(br#"{"seq":3,"recorded_at":"#)
+            .unwrap();
+
+        let replayed = MemoryRepository::default();
+        let mut log = EventLog::open_and_replay(&dir.0, 0, &replayed).unwrap();
+        assert_eq!(usernames(&replayed), ["a", "b"]);
+        assert_eq!(fs::metadata(&log_path).unwrap().len(), complete);
+        // The sequence picks up where the complete lines ended
+        record(&mut log, &replayed, &["c"]);
+        drop(log);
+        let again = MemoryRepository::default();
+        EventLog::open_and_replay(&dir.0, 0, &again).unwrap();
+        assert_eq!(usernames(&again), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn a_corrupt_complete_line_fails_the_boot() {
+        let dir = TempDir::new();
+        fs::create_dir_all(&dir.0).unwrap();
+        fs::write(dir.0.join("events.jsonl"), "not json\n").unwrap();
+        let result = EventLog::open_and_replay(&dir.0, 0, &MemoryRepository::default());
+        assert!(result.is_err_and(|e| e.contains("line 1")));
+    }
+
+    #[test]
+    fn snapshots_rotate_the_log() {
+        let dir = TempDir::new();
+        let repo = MemoryRepository::default();
+        let mut log = EventLog::open_and_replay(&dir.0, 2, &repo).unwrap();
+        record(&mut log, &repo, &["a", "b", "c"]);
+        drop(log);
+
+        // The snapshot at 2 archived the first two events
+        assert_eq!(read_snapshot(&dir.0.join("snapshot.json")).unwrap().seq, 2);
+        assert_eq!(lines(&dir.0.join("events-000000000002.jsonl")), 2);
+        assert_eq!(lines(&dir.0.join("events.jsonl")), 1);
+
+        let replayed = MemoryRepository::default();
+        let mut log = EventLog::open_and_replay(&dir.0, 2, &replayed).unwrap();
+        assert_eq!(usernames(&replayed), ["a", "b", "c"]);
+        record(&mut log, &replayed, &["d"]);
+        assert_eq!(read_snapshot(&dir.0.join("snapshot.json")).unwrap().seq, 4);
+        assert!(dir.0.join("events-000000000004.jsonl").exists());
+    }
+
+    #[test]
+    fn events_already_in_the_snapshot_are_skipped() {
+        let dir = TempDir::new();
+        let repo = MemoryRepository::default();
+        let mut log = EventLog::open_and_replay(&dir.0, 2, &repo).unwrap();
+        record(&mut log, &repo, &["a", "b"]);
+        drop(log);
+        // A crash between the snapshot and the rotation leaves both behind
+        fs::rename(
+            dir.0.join("events-000000000002.jsonl"),
+            dir.0.join("events.jsonl"),
+        )
+        .unwrap();
+
+        let replayed = MemoryRepository::default();
+        let mut log = EventLog::open_and_replay(&dir.0, 2, &replayed).unwrap();
+        assert_eq!(usernames(&replayed), ["a", "b"]);
+        assert_eq!(log.append(registered("c")).unwrap().seq, 3);
+    }
+
+    #[test]
+    fn a_retracted_event_does_not_replay() {
+        let dir = TempDir::new();
+        let repo = MemoryRepository::default();
+        let mut log = EventLog::open_and_replay(&dir.0, 0, &repo).unwrap();
+        record(&mut log, &repo, &["a"]);
+        let failed = log.append(registered("b")).unwrap();
+        log.retract(failed.seq).unwrap();
+        // Only the last append can be taken back
+        assert!(log.retract(1).is_err());
+        assert_eq!(log.append(registered("c")).unwrap().seq, 2);
+        drop(log);
+
+        let replayed = MemoryRepository::default();
+        EventLog::open_and_replay(&dir.0, 0, &replayed).unwrap();
+        assert_eq!(usernames(&replayed), ["a", "c"]);
+    }
+}
@@ -1,33 +1,158 @@
 use actix_cors::Cors;
-use actix_web::{App, HttpResponse, HttpServer, guard, web};
+use actix_web::{App, HttpMessage, HttpRequest, HttpResponse, HttpServer, guard, middleware, web};
+use admin::{AdminAction, AdminActionKind};
 use async_graphql::{
-    Context, EmptySubscription, Enum, ID, InputObject, Object, Schema, SimpleObject,
+    ComplexObject, Context, Enum, ErrorExtensions, ID, InputObject, Object, Schema, SimpleObject,
 };
-use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
+use auth::{AuthKeys, AuthPayload, RoleGuard, Session};
 use chrono::{DateTime, Utc};
-use parking_lot::RwLock;
+use copying::{
+    CopyFilters, CopyLimits, CopyRelationChange, CopyStatus, SizingMode, SkipReason, StopCopyMode,
+    UpdateCopySettingsInput,
+};
+use errors::{DomainError, DomainResult};
+use events::DomainEvent;
+use orders::{AmendOrderInput, Order, OrderStatus, PlaceOrderInput};
+use pagination::{
+    CopyRelationFilter, CopyRelationSort, ListConnection, TradeFilter, TradeSort, UserFilter,
+    UserSort, paginate,
+};
+use prices::{PricePool, PriceQuote};
 use serde::{Deserialize, Serialize};
-use snurr::Process;
 use std::collections::HashMap;
-use std::sync::Arc;
-use uuid::Uuid;
+use storage::{DbPool, Store};
+use subscriptions::SubscriptionRoot;
+use trading::AssetClass;
+use workflows::{WorkflowDefinition, WorkflowInstance, WorkflowInstanceFilter, WorkflowPool};
+
+mod accounting;
+mod admin;
+mod auth;
+mod copying;
+mod errors;
+mod events;
+mod monitor;
+mod orders;
+mod pagination;
+mod prices;
+mod stats;
+mod storage;
+mod subscriptions;
+mod trading;
+mod workflows;
 
 // ================= Data Models =================
 
-#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+// Performance fields (total_pnl .. worst_trade) are derived from closed trades
+// and copied trades; see stats.rs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SimpleObject)]
+#[graphql(complex)]
 pub struct User {
     pub id: ID,
     pub username: String,
     pub balance: f64,
+    #[serde(default)]
+    pub margin_used: f64,
     pub total_pnl: f64,
     pub win_rate: f64,
+    #[serde(default)]
+    pub trade_count: i32,
+    #[serde(default)]
+    pub winning_trades: i32,
+    #[serde(default)]
+    pub losing_trades: i32,
+    #[serde(default)]
+    pub gross_profit: f64,
+    #[serde(default)]
+    pub gross_loss: f64,
+    #[serde(default)]
+    pub best_trade: Option<f64>,
+    #[serde(default)]
+    pub worst_trade: Option<f64>,
     pub followers_count: i32,
     pub is_trader: bool,
+    // Operators with access to the admin mutations; see `role`
+    #[serde(default)]
+    #[graphql(skip)]
+    pub is_admin: bool,
+    // Suspended accounts cannot sign in or act
+    #[serde(default)]
+    pub suspended: bool,
     pub created_at: DateTime<Utc>,
+    // Argon2id PHC string; accounts without one cannot sign in
+    #[serde(default)]
+    #[graphql(skip)]
+    pub password_hash: Option<String>,
+}
+
+impl User {
+    // New account with no trading history
+    pub fn new(id: &str, username: &str, balance: f64, is_trader: bool) -> Self {
+        User {
+            id: ID(id.to_string()),
+            username: username.to_string(),
+            balance,
+            margin_used: 0.0,
+            total_pnl: 0.0,
+            win_rate: 0.0,
+            trade_count: 0,
+            winning_trades: 0,
+            losing_trades: 0,
+            gross_profit: 0.0,
+            gross_loss: 0.0,
+            best_trade: None,
+            worst_trade: None,
+            followers_count: 0,
+            is_trader,
+            is_admin: false,
+            suspended: false,
+            created_at: Utc::now(),
+            password_hash: None,
+        }
+    }
+
+    // Every user can follow; trading and admin access are granted separately
+    pub fn has_role(&self, role: Role) -> bool {
+        match role {
+            Role::Follower => true,
+            Role::Trader => self.is_trader,
+            Role::Admin => self.is_admin,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum Role {
+    Follower,
+    Trader,
+    Admin,
+}
+
+#[ComplexObject]
+impl User {
+    // Highest role the user holds
+    async fn role(&self) -> Role {
+        [Role::Admin, Role::Trader]
+            .into_iter()
+            .find(|role| self.has_role(*role))
+            .unwrap_or(Role::Follower)
+    }
+
+    // Average PnL of winning trades
+    async fn avg_win(&self) -> Option<f64> {
+        (self.winning_trades > 0).then(|| self.gross_profit / self.winning_trades as f64)
+    }
+
+    // Average PnL of losing trades (negative)
+    async fn avg_loss(&self) -> Option<f64> {
+        (self.losing_trades > 0).then(|| self.gross_loss / self.losing_trades as f64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Enum)]
 pub enum TradeDirection {
+    #[default]
     Long,
     Short,
 }
@@ -36,9 +161,23 @@ pub enum TradeDirection {
 pub enum TradeStatus {
     Open,
     Closed,
+    // Copy that was never opened; see CopiedTrade.skipReason
+    Skipped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum CloseReason {
+    Manual,
+    StopLoss,
+    TakeProfit,
+    // Closed by an admin with `forceCloseTrade`
+    Admin,
+    // Closed when the follower stopped copying with mode CLOSE
+    StopCopying,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+#[graphql(complex)]
 pub struct Trade {
     pub id: ID,
     pub trader_id: ID,
@@ -47,82 +186,297 @@ pub struct Trade {
     pub entry_price: f64,
     pub exit_price: Option<f64>,
     pub quantity: f64,
+    #[serde(default)]
+    pub margin: f64,
+    #[serde(default)]
+    pub stop_loss: Option<f64>,
+    #[serde(default)]
+    pub take_profit: Option<f64>,
     pub pnl: Option<f64>,
+    // PnL already booked by partial closes; `pnl` includes it once the position closes
+    #[serde(default)]
+    pub realized_pnl: f64,
     pub status: TradeStatus,
+    #[serde(default)]
+    pub close_reason: Option<CloseReason>,
+    // Create Trade workflow run that opened the trade
+    #[serde(default)]
+    pub workflow_instance_id: Option<ID>,
     pub created_at: DateTime<Utc>,
     pub closed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+#[graphql(complex)]
 pub struct CopyRelation {
     pub id: ID,
     pub follower_id: ID,
     pub trader_id: ID,
     pub copy_ratio: f64,
-    pub active: bool,
+    #[serde(default)]
+    pub sizing_mode: SizingMode,
+    // Quote amount per copied trade (FixedNotional)
+    #[serde(default)]
+    pub notional: Option<f64>,
+    // Capital set aside for this trader (AllocatedCapital)
+    #[serde(default)]
+    pub allocated_capital: Option<f64>,
+    // Follower overrides for copied trades, in percent from the entry price
+    #[serde(default)]
+    pub stop_loss_pct: Option<f64>,
+    #[serde(default)]
+    pub take_profit_pct: Option<f64>,
+    #[serde(default)]
+    pub limits: CopyLimits,
+    #[serde(default)]
+    pub filters: CopyFilters,
+    #[serde(alias = "active", deserialize_with = "copying::status_or_active")]
+    pub status: CopyStatus,
+    // How open copies were handled when the relation stopped
+    #[serde(default)]
+    pub stop_mode: Option<StopCopyMode>,
+    // Pauses, resumes and settings changes, oldest first
+    #[serde(default)]
+    pub settings_history: Vec<CopyRelationChange>,
+    // Realized PnL of the relation's copies when it last resumed from an
+    // auto-pause; the drawdown limit counts losses from here
+    #[serde(default)]
+    pub drawdown_baseline: f64,
+    // Copy Trader workflow run that created the relation
+    #[serde(default)]
+    pub workflow_instance_id: Option<ID>,
     pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+#[graphql(complex)]
 pub struct CopiedTrade {
     pub id: ID,
     pub original_trade_id: ID,
     pub follower_id: ID,
+    // Relation the copy was made under; missing on copies from older logs
+    #[serde(default)]
+    pub relation_id: Option<ID>,
+    // Copied instrument and side; older logs get them from the original trade on boot
+    #[serde(default)]
+    pub symbol: String,
+    #[serde(default)]
+    pub direction: TradeDirection,
+    // Follower's own fills, which may differ from the trader's (e.g. an open
+    // position copied at the mark when copying started)
+    #[serde(default, deserialize_with = "copying::price_or_null")]
+    pub entry_price: f64,
+    #[serde(default)]
+    pub exit_price: Option<f64>,
     pub quantity: f64,
+    #[serde(default)]
+    pub margin: f64,
+    // Set only when the relation overrides the trader's levels
+    #[serde(default)]
+    pub stop_loss: Option<f64>,
+    #[serde(default)]
+    pub take_profit: Option<f64>,
     pub pnl: Option<f64>,
+    // PnL already booked by partial closes; `pnl` includes it once the position closes
+    #[serde(default)]
+    pub realized_pnl: f64,
     pub status: TradeStatus,
+    #[serde(default)]
+    pub close_reason: Option<CloseReason>,
+    // Why the copy was not opened, when skipped
+    #[serde(default)]
+    pub skip_reason: Option<SkipReason>,
+    #[serde(default)]
+    pub skip_detail: Option<String>,
+    // Unlinked from the original trade when copying stopped; the follower
+    // closes it and the trader's changes no longer apply
+    #[serde(default)]
+    pub detached: bool,
+    #[serde(default)]
+    pub opened_at: DateTime<Utc>,
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
 }
 
-// ================= In-Memory Database =================
+#[ComplexObject]
+impl Trade {
+    // Current market price while the trade is open
+    async fn mark_price(&self, ctx: &Context<'_>) -> Option<f64> {
+        self.mark(ctx.data_unchecked::<PricePool>())
+    }
+
+    // PnL if the trade were closed at the mark price
+    async fn unrealized_pnl(&self, ctx: &Context<'_>) -> Option<f64> {
+        let mark = self.mark(ctx.data_unchecked::<PricePool>())?;
+        Some(accounting::realized_pnl(
+            self.direction,
+            self.entry_price,
+            mark,
+            self.quantity,
+        ))
+    }
 
-#[derive(Default)]
-pub struct Database {
-    pub users: HashMap<String, User>,
-    pub trades: HashMap<String, Trade>,
-    pub copy_relations: HashMap<String, CopyRelation>,
-    pub copied_trades: HashMap<String, CopiedTrade>,
+    async fn asset_class(&self) -> AssetClass {
+        trading::asset_class(&self.symbol)
+    }
+
+    // Workflow run that opened the trade
+    async fn workflow_instance(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<WorkflowInstance>> {
+        workflow_instance(ctx, &self.workflow_instance_id)
+    }
 }
 
-pub type DbPool = Arc<RwLock<Database>>;
+#[ComplexObject]
+impl CopyRelation {
+    // Trades this relation did not copy, with the reason on each
+    async fn skipped_trades(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<CopiedTrade>> {
+        let mut copies = copying::relation_copies(ctx.data_unchecked::<DbPool>(), self)
+            .map_err(|e| e.extend())?;
+        copies.retain(|ct| ct.status == TradeStatus::Skipped);
+        Ok(copies)
+    }
 
-// Initialize database with sample users and trades
-fn init_sample_data(db: &mut Database) {
-    // Traders
-    let trader1 = User {
-        id: ID("trader1".to_string()),
-        username: "AlphaTrader".to_string(),
-        balance: 100000.0,
-        total_pnl: 15420.50,
-        win_rate: 0.72,
-        followers_count: 156,
-        is_trader: true,
-        created_at: Utc::now(),
+    // Workflow run that created the relation
+    async fn workflow_instance(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<WorkflowInstance>> {
+        workflow_instance(ctx, &self.workflow_instance_id)
+    }
+}
+
+fn workflow_instance(
+    ctx: &Context<'_>,
+    id: &Option<ID>,
+) -> async_graphql::Result<Option<WorkflowInstance>> {
+    let Some(id) = id else {
+        return Ok(None);
     };
-    let trader2 = User {
-        id: ID("trader2".to_string()),
-        username: "CryptoKing".to_string(),
-        balance: 250000.0,
-        total_pnl: 42350.0,
-        win_rate: 0.68,
-        followers_count: 312,
-        is_trader: true,
-        created_at: Utc::now(),
+    ctx.data_unchecked::<DbPool>()
+        .get_workflow_instance(id)
+        .map_err(|e| DomainError::from(e).extend())
+}
+
+impl Trade {
+    fn mark(&self, prices: &PricePool) -> Option<f64> {
+        match self.status {
+            TradeStatus::Open => prices.price(&self.symbol),
+            TradeStatus::Closed | TradeStatus::Skipped => None,
+        }
+    }
+}
+
+#[ComplexObject]
+impl CopiedTrade {
+    // Current market price of the copied symbol while the copy is open
+    async fn mark_price(&self, ctx: &Context<'_>) -> Option<f64> {
+        self.mark(ctx.data_unchecked::<PricePool>())
+    }
+
+    // Follower's PnL if the copy were closed at the mark price
+    async fn unrealized_pnl(&self, ctx: &Context<'_>) -> Option<f64> {
+        let mark = self.mark(ctx.data_unchecked::<PricePool>())?;
+        Some(accounting::realized_pnl(
+            self.direction,
+            self.entry_price,
+            mark,
+            self.quantity,
+        ))
+    }
+}
+
+impl CopiedTrade {
+    fn mark(&self, prices: &PricePool) -> Option<f64> {
+        match self.status {
+            TradeStatus::Open => prices.price(&self.symbol),
+            TradeStatus::Closed | TradeStatus::Skipped => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum BalanceEntryKind {
+    MarginReserved,
+    MarginReleased,
+    RealizedPnl,
+    // Manual correction by an admin
+    Adjustment,
+}
+
+// One movement of a user's balance
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+pub struct BalanceEntry {
+    pub id: ID,
+    pub user_id: ID,
+    pub kind: BalanceEntryKind,
+    pub amount: f64,
+    pub balance_after: f64,
+    pub reference_id: ID,
+    pub created_at: DateTime<Utc>,
+}
+
+// ================= Sample Data =================
+
+// Password of every sample account
+const SAMPLE_PASSWORD: &str = "copytrade";
+
+// Seed an empty repository. SAMPLE_DATA=true loads the sample users and trades,
+// whose accounts share the public SAMPLE_PASSWORD; otherwise ADMIN_PASSWORD, when
+// set, creates just the operator account.
+fn seed_from_env(db: &Store) -> Result<(), String> {
+    let sample = match std::env::var("SAMPLE_DATA") {
+        Ok(v) => v
+            .parse()
+            .map_err(|_| format!("Invalid SAMPLE_DATA: {}", v))?,
+        Err(_) => false,
     };
-    // Regular user
-    let user1 = User {
-        id: ID("user1".to_string()),
-        username: "NewInvestor".to_string(),
-        balance: 10000.0,
-        total_pnl: 520.0,
-        win_rate: 0.65,
-        followers_count: 0,
-        is_trader: false,
-        created_at: Utc::now(),
+    if sample {
+        println!(
+            "⚠️ Seeding sample data; sample accounts use the password \"{}\"",
+            SAMPLE_PASSWORD
+        );
+        return init_sample_data(db);
+    }
+    let Ok(password) = std::env::var("ADMIN_PASSWORD") else {
+        println!("⚠️ Empty store and no ADMIN_PASSWORD, starting without an admin");
+        return Ok(());
     };
+    if password.len() < auth::MIN_PASSWORD_LEN {
+        return Err(format!(
+            "ADMIN_PASSWORD must be at least {} characters",
+            auth::MIN_PASSWORD_LEN
+        ));
+    }
+    let mut admin = operator();
+    admin.password_hash = Some(auth::hash_password(&password).map_err(|e| e.to_string())?);
+    println!("🔑 Created admin account: {}", admin.username);
+    db.record(DomainEvent::UserRegistered { user: admin })
+}
+
+fn operator() -> User {
+    let mut admin1 = User::new("admin1", "Operator", 0.0, false);
+    admin1.is_admin = true;
+    admin1
+}
 
-    db.users.insert(trader1.id.to_string(), trader1);
-    db.users.insert(trader2.id.to_string(), trader2);
-    db.users.insert(user1.id.to_string(), user1);
+// Seed an empty repository with sample users and trades
+fn init_sample_data(db: &Store) -> Result<(), String> {
+    // Traders
+    let trader1 = User::new("trader1", "AlphaTrader", 100000.0, true);
+    let trader2 = User::new("trader2", "CryptoKing", 250000.0, true);
+    // Regular user
+    let user1 = User::new("user1", "NewInvestor", 10000.0, false);
+    // Operator
+    let admin1 = operator();
+
+    let password_hash = auth::hash_password(SAMPLE_PASSWORD).map_err(|e| e.to_string())?;
+    for mut user in [trader1, trader2, user1, admin1] {
+        user.password_hash = Some(password_hash.clone());
+        db.record(DomainEvent::UserRegistered { user })?;
+    }
 
     // Trades
     let trade1 = Trade {
@@ -133,8 +487,14 @@ fn init_sample_data(db: &mut Database) {
         entry_price: 42500.0,
         exit_price: None,
         quantity: 0.5,
+        margin: accounting::margin_for(42500.0, 0.5),
+        stop_loss: None,
+        take_profit: None,
         pnl: None,
+        realized_pnl: 0.0,
         status: TradeStatus::Open,
+        close_reason: None,
+        workflow_instance_id: None,
         created_at: Utc::now(),
         closed_at: None,
     };
@@ -146,457 +506,739 @@ fn init_sample_data(db: &mut Database) {
         entry_price: 2250.0,
         exit_price: Some(2380.0),
         quantity: 5.0,
+        margin: accounting::margin_for(2250.0, 5.0),
+        stop_loss: None,
+        take_profit: None,
         pnl: Some(650.0),
+        realized_pnl: 0.0,
         status: TradeStatus::Closed,
+        close_reason: Some(CloseReason::Manual),
+        workflow_instance_id: None,
         created_at: Utc::now(),
         closed_at: Some(Utc::now()),
     };
 
-    db.trades.insert(trade1.id.to_string(), trade1);
-    db.trades.insert(trade2.id.to_string(), trade2);
+    accounting::reserve_margin(db, &trade1.trader_id, trade1.margin, &trade1.id)
+        .map_err(|e| e.to_string())?;
+    db.record(DomainEvent::TradeOpened { trade: trade1 })?;
+    db.record(DomainEvent::TradeClosed { trade: trade2 })?;
+    Ok(())
 }
 
-// ================= BPMN Workflow Contexts =================
+// ================= GraphQL Schema =================
 
-#[derive(Default)]
-pub struct TradeWorkflowCtx {
-    pub trader_id: String,
-    pub symbol: String,
-    pub direction: String,
-    pub entry_price: f64,
-    pub quantity: f64,
-    pub trade_id: String,
-    pub is_valid: bool,
-    pub error: Option<String>,
-    pub db: Option<DbPool>,
-}
+pub struct QueryRoot;
 
-#[derive(Default)]
-pub struct CopyWorkflowCtx {
-    pub follower_id: String,
-    pub trader_id: String,
-    pub copy_ratio: f64,
-    pub relation_id: String,
-    pub is_valid: bool,
-    pub error: Option<String>,
-    pub db: Option<DbPool>,
-}
-
-// ================= BPMN Workflow Execution =================
-
-// Execute Create Trade workflow using BPMN
-pub fn execute_create_trade(db: DbPool, input: &CreateTradeInput) -> Result<Trade, String> {
-    println!("🔄 BPMN: Starting Create Trade workflow");
-
-    let process = Process::<TradeWorkflowCtx>::new("bpmn/create_trade.bpmn")
-        .map_err(|e| format!("BPMN parse error: {:?}", e))?
-        // Validate trade input (quantity and entry price)
-        .task("Validate Trade Input", |ctx| {
-            println!("  📋 Task: Validate Trade Input");
-            let mut guard = ctx.lock().unwrap();
-            if guard.quantity <= 0.0 {
-                guard.is_valid = false;
-                guard.error = Some("Invalid quantity".to_string());
-            } else if guard.entry_price <= 0.0 {
-                guard.is_valid = false;
-                guard.error = Some("Invalid price".to_string());
-            } else {
-                guard.is_valid = true;
-            }
-            println!(
-                "    ✅ Validation: {}",
-                if guard.is_valid { "PASSED" } else { "FAILED" }
-            );
-            None
-        })
-        // Conditional gateway: proceed only if input is valid
-        .exclusive("Is Valid", |ctx| {
-            let guard = ctx.lock().unwrap();
-            if guard.is_valid { "Yes" } else { "No" }.into()
-        })
-        // Create trade record in DB
-        .task("Create Trade Record", |ctx| {
-            println!("  💾 Task: Create Trade Record");
-            let mut guard = ctx.lock().unwrap();
-            let trade_id = Uuid::new_v4().to_string();
-            let direction = if guard.direction == "Long" {
-                TradeDirection::Long
-            } else {
-                TradeDirection::Short
-            };
-            let trade = Trade {
-                id: ID(trade_id.clone()),
-                trader_id: ID(guard.trader_id.clone()),
-                symbol: guard.symbol.clone(),
-                direction,
-                entry_price: guard.entry_price,
-                exit_price: None,
-                quantity: guard.quantity,
-                pnl: None,
-                status: TradeStatus::Open,
-                created_at: Utc::now(),
-                closed_at: None,
-            };
-            if let Some(ref db) = guard.db {
-                db.write().trades.insert(trade_id.clone(), trade);
-            }
-            guard.trade_id = trade_id.clone();
-            println!("    ✅ Trade created: {}", trade_id);
-            None
-        })
-        // Copy trade to active followers
-        .task("Copy Trade To Followers", |ctx| {
-            println!("  👥 Task: Copy Trade To Followers");
-            let guard = ctx.lock().unwrap();
-            if let Some(ref db) = guard.db {
-                let mut db_lock = db.write();
-                let followers: Vec<CopyRelation> = db_lock
-                    .copy_relations
-                    .values()
-                    .filter(|r| r.trader_id.to_string() == guard.trader_id && r.active)
-                    .cloned()
-                    .collect();
-                let count = followers.len();
-                for relation in followers {
-                    let copied_trade_id = Uuid::new_v4().to_string();
-                    let copied_trade = CopiedTrade {
-                        id: ID(copied_trade_id.clone()),
-                        original_trade_id: ID(guard.trade_id.clone()),
-                        follower_id: relation.follower_id,
-                        quantity: guard.quantity * relation.copy_ratio,
-                        pnl: None,
-                        status: TradeStatus::Open,
-                    };
-                    db_lock.copied_trades.insert(copied_trade_id, copied_trade);
-                }
-                println!("    ✅ Copied to {} followers", count);
-            }
-            None
-        })
-        .build()
-        .map_err(|e| format!("BPMN build error: {:?}", e))?;
-
-    // Prepare workflow context
-    let ctx = TradeWorkflowCtx {
-        trader_id: input.trader_id.to_string(),
-        symbol: input.symbol.clone(),
-        direction: format!("{:?}", input.direction),
-        entry_price: input.entry_price,
-        quantity: input.quantity,
-        trade_id: String::new(),
-        is_valid: false,
-        error: None,
-        db: Some(db.clone()),
-    };
+#[Object]
+impl QueryRoot {
+    // Traders, newest first unless sorted otherwise
+    #[allow(clippy::too_many_arguments)]
+    async fn traders(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<UserFilter>,
+        sort: Option<UserSort>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> async_graphql::Result<ListConnection<User>> {
+        let filter = UserFilter {
+            is_trader: Some(true),
+            ..filter.unwrap_or_default()
+        };
+        list_users(ctx, filter, sort, after, before, first, last).await
+    }
 
-    // Execute workflow
-    let result = process
-        .run(ctx)
-        .map_err(|e| format!("Workflow error: {:?}", e))?;
-    println!("✅ BPMN: Workflow completed");
-
-    // Retrieve created trade from DB
-    if !result.trade_id.is_empty() {
-        let db_lock = db.read();
-        if let Some(trade) = db_lock.trades.get(&result.trade_id) {
-            return Ok(trade.clone());
-        }
+    // The signed-in user, if any
+    async fn me(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<User>> {
+        let Ok(id) = auth::acting_user(ctx) else {
+            return Ok(None);
+        };
+        ctx.data_unchecked::<DbPool>()
+            .get_user(&id)
+            .map_err(|e| DomainError::from(e).extend())
     }
 
-    Err(result
-        .error
-        .unwrap_or_else(|| "Trade creation failed".to_string()))
-}
-
-// Execute Copy Trader workflow using BPMN
-pub fn execute_copy_trader(db: DbPool, input: &CopyTraderInput) -> Result<CopyRelation, String> {
-    println!("🔄 BPMN: Starting Copy Trader workflow");
-    let bpmn = "./bpmn/copy_trader.bpmn";
-    let process = Process::<CopyWorkflowCtx>::new(bpmn)
-        .map_err(|e| format!("BPMN parse error: {:?}", e))?
-        // Validate copy request (ratio 0.01..1.0)
-        .task("Validate Copy Request", |ctx| {
-            println!("  📋 Task: Validate Copy Request");
-            let mut guard = ctx.lock().unwrap();
-            if guard.copy_ratio < 0.01 || guard.copy_ratio > 1.0 {
-                guard.is_valid = false;
-                guard.error = Some("Invalid copy ratio".to_string());
-            } else {
-                guard.is_valid = true;
-            }
-            println!(
-                "    ✅ Validation: {}",
-                if guard.is_valid { "PASSED" } else { "FAILED" }
-            );
-            None
-        })
-        // Conditional gateway: proceed only if valid
-        .exclusive("Is Valid", |ctx| {
-            let guard = ctx.lock().unwrap();
-            if guard.is_valid { "Yes" } else { "No" }.into()
-        })
-        // Create copy relation in DB
-        .task("Create Copy Relation", |ctx| {
-            println!("  💾 Task: Create Copy Relation");
-            let mut guard = ctx.lock().unwrap();
-            let relation_id = Uuid::new_v4().to_string();
-            let relation = CopyRelation {
-                id: ID(relation_id.clone()),
-                follower_id: ID(guard.follower_id.clone()),
-                trader_id: ID(guard.trader_id.clone()),
-                copy_ratio: guard.copy_ratio,
-                active: true,
-                created_at: Utc::now(),
-            };
-            if let Some(ref db) = guard.db {
-                db.write()
-                    .copy_relations
-                    .insert(relation_id.clone(), relation);
-            }
-            guard.relation_id = relation_id.clone();
-            println!("    ✅ Relation created: {}", relation_id);
-            None
-        })
-        // Update trader's follower count
-        .task("Update Follower Count", |ctx| {
-            println!("  📊 Task: Update Follower Count");
-            let guard = ctx.lock().unwrap();
-            if let Some(ref db) = guard.db {
-                let mut db_lock = db.write();
-                if let Some(trader) = db_lock.users.get_mut(&guard.trader_id) {
-                    trader.followers_count += 1;
-                    println!("    ✅ Count: {}", trader.followers_count);
-                }
-            }
-            None
-        })
-        .build()
-        .map_err(|e| format!("BPMN build error: {:?}", e))?;
-
-    // Prepare workflow context
-    let ctx = CopyWorkflowCtx {
-        follower_id: input.follower_id.to_string(),
-        trader_id: input.trader_id.to_string(),
-        copy_ratio: input.copy_ratio,
-        relation_id: String::new(),
-        is_valid: false,
-        error: None,
-        db: Some(db.clone()),
-    };
+    // Fetch a specific user
+    async fn user(&self, ctx: &Context<'_>, id: ID) -> async_graphql::Result<Option<User>> {
+        ctx.data_unchecked::<DbPool>()
+            .get_user(&id)
+            .map_err(|e| DomainError::from(e).extend())
+    }
 
-    // Execute workflow
-    let result = process
-        .run(ctx)
-        .map_err(|e| format!("Workflow error: {:?}", e))?;
-    println!("✅ BPMN: Workflow completed");
-
-    // Retrieve created relation from DB
-    if !result.relation_id.is_empty() {
-        let db_lock = db.read();
-        if let Some(relation) = db_lock.copy_relations.get(&result.relation_id) {
-            return Ok(relation.clone());
-        }
+    // Users, newest first unless sorted otherwise
+    #[allow(clippy::too_many_arguments)]
+    async fn users(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<UserFilter>,
+        sort: Option<UserSort>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> async_graphql::Result<ListConnection<User>> {
+        let filter = filter.unwrap_or_default();
+        list_users(ctx, filter, sort, after, before, first, last).await
     }
 
-    Err(result.error.unwrap_or_else(|| "Copy failed".to_string()))
-}
+    // Trades, newest first unless sorted otherwise
+    #[allow(clippy::too_many_arguments)]
+    async fn trades(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<TradeFilter>,
+        sort: Option<TradeSort>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> async_graphql::Result<ListConnection<Trade>> {
+        let filter = filter.unwrap_or_default();
+        list_trades(ctx, filter, sort, after, before, first, last).await
+    }
 
-// ================= GraphQL Schema =================
+    // Open trades, newest first unless sorted otherwise
+    #[allow(clippy::too_many_arguments)]
+    async fn open_trades(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<TradeFilter>,
+        sort: Option<TradeSort>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> async_graphql::Result<ListConnection<Trade>> {
+        let filter = TradeFilter {
+            status: Some(TradeStatus::Open),
+            ..filter.unwrap_or_default()
+        };
+        list_trades(ctx, filter, sort, after, before, first, last).await
+    }
 
-pub struct QueryRoot;
+    // The signed-in user's copy relations (active only unless filtered), newest
+    // first unless sorted otherwise
+    #[allow(clippy::too_many_arguments)]
+    async fn my_copy_relations(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<CopyRelationFilter>,
+        sort: Option<CopyRelationSort>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> async_graphql::Result<ListConnection<CopyRelation>> {
+        let follower_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let filter = filter.unwrap_or_default();
+        let mut relations = ctx
+            .data_unchecked::<DbPool>()
+            .copy_relations_by_follower(&follower_id)
+            .map_err(|e| DomainError::from(e).extend())?;
+        relations.retain(|r| filter.matches(r));
+        let sort = sort.unwrap_or_default();
+        paginate(relations, &sort, after, before, first, last).await
+    }
 
-#[Object]
-impl QueryRoot {
-    // Fetch all traders
-    async fn traders(&self, ctx: &Context<'_>) -> Vec<User> {
-        ctx.data_unchecked::<DbPool>()
-            .read()
-            .users
-            .values()
-            .filter(|u| u.is_trader)
-            .cloned()
-            .collect()
+    // Latest simulated market price for a symbol
+    async fn price(&self, ctx: &Context<'_>, symbol: String) -> Option<PriceQuote> {
+        ctx.data_unchecked::<PricePool>().quote(&symbol)
     }
 
-    // Fetch a specific user
-    async fn user(&self, ctx: &Context<'_>, id: ID) -> Option<User> {
-        ctx.data_unchecked::<DbPool>()
-            .read()
-            .users
-            .get(&id.to_string())
-            .cloned()
+    // The signed-in user's balance ledger, oldest first
+    async fn balance_ledger(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<BalanceEntry>> {
+        let user_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let mut entries = ctx
+            .data_unchecked::<DbPool>()
+            .balance_entries_by_user(&user_id)
+            .map_err(|e| DomainError::from(e).extend())?;
+        entries.sort_by_key(|e| e.created_at);
+        Ok(entries)
     }
 
-    // Fetch all users
-    async fn users(&self, ctx: &Context<'_>) -> Vec<User> {
-        ctx.data_unchecked::<DbPool>()
-            .read()
-            .users
-            .values()
-            .cloned()
-            .collect()
-    }
-
-    // Fetch trades, optionally filtered by trader
-    async fn trades(&self, ctx: &Context<'_>, trader_id: Option<ID>) -> Vec<Trade> {
-        let db = ctx.data_unchecked::<DbPool>().read();
-        match trader_id {
-            Some(id) => db
-                .trades
-                .values()
-                .filter(|t| t.trader_id == id)
-                .cloned()
-                .collect(),
-            None => db.trades.values().cloned().collect(),
-        }
+    // The signed-in user's copied trades, filtered and sorted by their own
+    // symbol, direction, status and open time (newest first by default)
+    #[allow(clippy::too_many_arguments)]
+    async fn my_copied_trades(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<TradeFilter>,
+        sort: Option<TradeSort>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> async_graphql::Result<ListConnection<CopiedTrade>> {
+        let follower_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let db = ctx.data_unchecked::<DbPool>();
+        let filter = filter.unwrap_or_default();
+        // A copy's trader is the trader of its relation
+        let traders: HashMap<ID, ID> = db
+            .copy_relations_by_follower(&follower_id)
+            .map_err(|e| DomainError::from(e).extend())?
+            .into_iter()
+            .map(|r| (r.id, r.trader_id))
+            .collect();
+        let mut copies = db
+            .copied_trades_by_follower(&follower_id)
+            .map_err(|e| DomainError::from(e).extend())?;
+        copies.retain(|ct| {
+            let trader_id = ct.relation_id.as_ref().and_then(|id| traders.get(id));
+            filter.matches_copy(ct, trader_id)
+        });
+        paginate(
+            copies,
+            &sort.unwrap_or_default(),
+            after,
+            before,
+            first,
+            last,
+        )
+        .await
     }
 
-    // Fetch all open trades
-    async fn open_trades(&self, ctx: &Context<'_>) -> Vec<Trade> {
-        ctx.data_unchecked::<DbPool>()
-            .read()
-            .trades
-            .values()
-            .filter(|t| t.status == TradeStatus::Open)
-            .cloned()
-            .collect()
+    // The signed-in trader's orders, optionally filtered by status, newest first
+    #[graphql(guard = "RoleGuard::new(Role::Trader)")]
+    async fn orders(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<OrderStatus>,
+    ) -> async_graphql::Result<Vec<Order>> {
+        let trader_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let mut orders = ctx
+            .data_unchecked::<DbPool>()
+            .orders_by_trader(&trader_id)
+            .map_err(|e| DomainError::from(e).extend())?;
+        orders.retain(|o| status.is_none_or(|s| o.status == s));
+        orders.sort_by_key(|o| std::cmp::Reverse(o.created_at));
+        Ok(orders)
     }
 
-    // Fetch active copy relations for a follower
-    async fn my_copy_relations(&self, ctx: &Context<'_>, follower_id: ID) -> Vec<CopyRelation> {
-        ctx.data_unchecked::<DbPool>()
-            .read()
-            .copy_relations
-            .values()
-            .filter(|r| r.follower_id == follower_id && r.active)
-            .cloned()
-            .collect()
+    // Admin audit log, newest first
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn admin_actions(
+        &self,
+        ctx: &Context<'_>,
+        target_id: Option<ID>,
+        kind: Option<AdminActionKind>,
+    ) -> async_graphql::Result<Vec<AdminAction>> {
+        let mut actions = ctx
+            .data_unchecked::<DbPool>()
+            .list_admin_actions()
+            .map_err(|e| DomainError::from(e).extend())?;
+        actions.retain(|a| {
+            target_id.as_ref().is_none_or(|id| a.target_id == *id)
+                && kind.is_none_or(|k| a.kind == k)
+        });
+        actions.sort_by_key(|a| std::cmp::Reverse(a.created_at));
+        Ok(actions)
     }
 
-    // Fetch copied trades for a follower
-    async fn my_copied_trades(&self, ctx: &Context<'_>, follower_id: ID) -> Vec<CopiedTrade> {
-        ctx.data_unchecked::<DbPool>()
-            .read()
-            .copied_trades
-            .values()
-            .filter(|t| t.follower_id == follower_id)
-            .cloned()
-            .collect()
+    // Recorded workflow runs matching the filter, newest first
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn workflow_instances(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<WorkflowInstanceFilter>,
+    ) -> async_graphql::Result<Vec<WorkflowInstance>> {
+        let filter = filter.unwrap_or_default();
+        let mut instances = ctx
+            .data_unchecked::<DbPool>()
+            .list_workflow_instances()
+            .map_err(|e| DomainError::from(e).extend())?;
+        instances.retain(|i| filter.matches(i));
+        instances.sort_by_key(|i| std::cmp::Reverse(i.started_at));
+        Ok(instances)
+    }
+
+    // A single recorded workflow run
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn workflow_instance(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+    ) -> async_graphql::Result<Option<WorkflowInstance>> {
+        workflow_instance(ctx, &Some(id))
+    }
+
+    // Loaded BPMN definitions with their version history, newest version first
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn workflow_definitions(
+        &self,
+        ctx: &Context<'_>,
+        name: Option<String>,
+    ) -> Vec<WorkflowDefinition> {
+        ctx.data_unchecked::<WorkflowPool>()
+            .definitions(name.as_deref())
     }
 }
 
 #[derive(InputObject)]
 pub struct CreateTradeInput {
+    // The signed-in trader; set by the server
+    #[graphql(skip)]
     pub trader_id: ID,
     pub symbol: String,
     pub direction: TradeDirection,
     pub entry_price: f64,
     pub quantity: f64,
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
 }
 
 #[derive(InputObject)]
 pub struct CopyTraderInput {
+    // The signed-in follower; set by the server
+    #[graphql(skip)]
     pub follower_id: ID,
     pub trader_id: ID,
+    // Used by FIXED_RATIO sizing
+    #[graphql(default = 1.0)]
     pub copy_ratio: f64,
+    #[graphql(default)]
+    pub sizing_mode: SizingMode,
+    // Required for FIXED_NOTIONAL sizing
+    pub notional: Option<f64>,
+    // Required for ALLOCATED_CAPITAL sizing
+    pub allocated_capital: Option<f64>,
+    // Override the trader's stop loss / take profit, in percent from entry
+    pub stop_loss_pct: Option<f64>,
+    pub take_profit_pct: Option<f64>,
+    #[graphql(default)]
+    pub limits: CopyLimits,
+    #[graphql(default)]
+    pub filters: CopyFilters,
+    // Also copy the trader's open trades, at the current mark price
+    #[graphql(default)]
+    pub copy_open_positions: bool,
+}
+
+async fn list_users(
+    ctx: &Context<'_>,
+    filter: UserFilter,
+    sort: Option<UserSort>,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> async_graphql::Result<ListConnection<User>> {
+    let mut users = ctx
+        .data_unchecked::<DbPool>()
+        .list_users()
+        .map_err(|e| DomainError::from(e).extend())?;
+    users.retain(|u| filter.matches(u));
+    paginate(users, &sort.unwrap_or_default(), after, before, first, last).await
+}
+
+async fn list_trades(
+    ctx: &Context<'_>,
+    filter: TradeFilter,
+    sort: Option<TradeSort>,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> async_graphql::Result<ListConnection<Trade>> {
+    let db = ctx.data_unchecked::<DbPool>();
+    let mut trades = match &filter.trader_id {
+        Some(id) => db
+            .trades_by_trader(id)
+            .map_err(|e| DomainError::from(e).extend())?,
+        None => db
+            .list_trades()
+            .map_err(|e| DomainError::from(e).extend())?,
+    };
+    trades.retain(|t| filter.matches(t));
+    paginate(
+        trades,
+        &sort.unwrap_or_default(),
+        after,
+        before,
+        first,
+        last,
+    )
+    .await
 }
 
 pub struct MutationRoot;
 
 #[Object]
 impl MutationRoot {
-    // Create a new trade
+    // Open a trade as the signed-in trader. The symbol must be quoted and the
+    // entry price close to its mark.
+    #[graphql(guard = "RoleGuard::new(Role::Trader)")]
     async fn create_trade(
         &self,
         ctx: &Context<'_>,
-        input: CreateTradeInput,
+        mut input: CreateTradeInput,
     ) -> async_graphql::Result<Trade> {
+        input.trader_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let prices = ctx.data_unchecked::<PricePool>();
+        trading::execution_price(prices, &input.symbol, "entryPrice", Some(input.entry_price))
+            .map_err(|e| e.extend())?;
         let db = ctx.data_unchecked::<DbPool>().clone();
-        execute_create_trade(db, &input).map_err(async_graphql::Error::new)
-    }
-
-    // Close an existing trade
-    async fn close_trade(&self, ctx: &Context<'_>, trade_id: ID, exit_price: f64) -> Option<Trade> {
-        let mut db = ctx.data_unchecked::<DbPool>().write();
-        if let Some(trade) = db.trades.get_mut(&trade_id.to_string()) {
-            let pnl = match trade.direction {
-                TradeDirection::Long => (exit_price - trade.entry_price) * trade.quantity,
-                TradeDirection::Short => (trade.entry_price - exit_price) * trade.quantity,
-            };
-            trade.exit_price = Some(exit_price);
-            trade.pnl = Some(pnl);
-            trade.status = TradeStatus::Closed;
-            trade.closed_at = Some(Utc::now());
-            let closed = trade.clone();
-
-            // Update copied trades for followers
-            for ct in db
-                .copied_trades
-                .values_mut()
-                .filter(|ct| ct.original_trade_id == trade_id)
-            {
-                ct.pnl = Some(match closed.direction {
-                    TradeDirection::Long => (exit_price - closed.entry_price) * ct.quantity,
-                    TradeDirection::Short => (closed.entry_price - exit_price) * ct.quantity,
-                });
-                ct.status = TradeStatus::Closed;
-            }
-            return Some(closed);
-        }
-        None
+        ctx.data_unchecked::<WorkflowPool>()
+            .create_trade(db, &input)
+            .map_err(|e| e.extend())
+    }
+
+    // Close one of the signed-in trader's trades at the mark price, or at
+    // `exit_price` if it is close to the mark
+    #[graphql(guard = "RoleGuard::new(Role::Trader)")]
+    async fn close_trade(
+        &self,
+        ctx: &Context<'_>,
+        trade_id: ID,
+        exit_price: Option<f64>,
+    ) -> async_graphql::Result<Trade> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let db = ctx.data_unchecked::<DbPool>().clone();
+        // Ownership is checked by the workflow's Validate step
+        let trade = db
+            .get_trade(&trade_id)
+            .map_err(|e| DomainError::from(e).extend())?
+            .ok_or_else(|| DomainError::not_found("Trade", &trade_id).extend())?;
+        let prices = ctx.data_unchecked::<PricePool>();
+        let exit_price = trading::execution_price(prices, &trade.symbol, "exitPrice", exit_price)
+            .map_err(|e| e.extend())?;
+        ctx.data_unchecked::<WorkflowPool>()
+            .close_trade(db, &actor, &trade_id, exit_price)
+            .map_err(|e| e.extend())
+    }
+
+    // Close part of an open trade and the same fraction of every copy, priced
+    // like closeTrade
+    #[graphql(guard = "RoleGuard::new(Role::Trader)")]
+    async fn close_trade_partial(
+        &self,
+        ctx: &Context<'_>,
+        trade_id: ID,
+        quantity: f64,
+        exit_price: Option<f64>,
+    ) -> async_graphql::Result<Trade> {
+        let db = ctx.data_unchecked::<DbPool>();
+        let trade = own_trade(ctx, db, &trade_id).map_err(|e| e.extend())?;
+        let prices = ctx.data_unchecked::<PricePool>();
+        let exit_price = trading::execution_price(prices, &trade.symbol, "exitPrice", exit_price)
+            .map_err(|e| e.extend())?;
+        trading::close_trade_partial(db, &trade_id, quantity, exit_price).map_err(|e| e.extend())
     }
 
-    // Copy a trader
+    // Add to an open trade at the mark price, or at `price` if it is close to
+    // the mark, scaling every copy by the same fraction
+    #[graphql(guard = "RoleGuard::new(Role::Trader)")]
+    async fn increase_trade(
+        &self,
+        ctx: &Context<'_>,
+        trade_id: ID,
+        quantity: f64,
+        price: Option<f64>,
+    ) -> async_graphql::Result<Trade> {
+        let db = ctx.data_unchecked::<DbPool>();
+        let trade = own_trade(ctx, db, &trade_id).map_err(|e| e.extend())?;
+        let prices = ctx.data_unchecked::<PricePool>();
+        let price = trading::execution_price(prices, &trade.symbol, "price", price)
+            .map_err(|e| e.extend())?;
+        trading::increase_trade(db, &trade_id, quantity, price).map_err(|e| e.extend())
+    }
+
+    // Start copying a trader as the signed-in follower
     async fn copy_trader(
         &self,
         ctx: &Context<'_>,
-        input: CopyTraderInput,
+        mut input: CopyTraderInput,
     ) -> async_graphql::Result<CopyRelation> {
+        input.follower_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
         let db = ctx.data_unchecked::<DbPool>().clone();
-        execute_copy_trader(db, &input).map_err(async_graphql::Error::new)
-    }
-
-    // Stop copying a trader
-    async fn stop_copying(&self, ctx: &Context<'_>, relation_id: ID) -> Option<CopyRelation> {
-        let mut db = ctx.data_unchecked::<DbPool>().write();
-        if let Some(rel) = db.copy_relations.get_mut(&relation_id.to_string()) {
-            rel.active = false;
-            let trader_id = rel.trader_id.to_string();
-            let result = rel.clone();
-            if let Some(trader) = db.users.get_mut(&trader_id) {
-                trader.followers_count = (trader.followers_count - 1).max(0);
-            }
-            return Some(result);
-        }
-        None
+        ctx.data_unchecked::<WorkflowPool>()
+            .copy_trader(db, ctx.data_unchecked::<PricePool>().clone(), &input)
+            .map_err(|e| e.extend())
     }
 
-    // Register a new user
-    async fn register_user(&self, ctx: &Context<'_>, username: String, is_trader: bool) -> User {
-        let mut db = ctx.data_unchecked::<DbPool>().write();
-        let user = User {
-            id: ID(Uuid::new_v4().to_string()),
-            username,
-            balance: 10000.0,
-            total_pnl: 0.0,
-            win_rate: 0.0,
-            followers_count: 0,
-            is_trader,
-            created_at: Utc::now(),
-        };
-        db.users.insert(user.id.to_string(), user.clone());
-        user
+    // Stop one of the signed-in follower's copy relations. `mode` decides
+    // whether its open copies are closed, kept or detached.
+    async fn stop_copying(
+        &self,
+        ctx: &Context<'_>,
+        relation_id: ID,
+        #[graphql(default)] mode: StopCopyMode,
+    ) -> async_graphql::Result<CopyRelation> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let db = ctx.data_unchecked::<DbPool>().clone();
+        let prices = ctx.data_unchecked::<PricePool>().clone();
+        ctx.data_unchecked::<WorkflowPool>()
+            .stop_copying(db, prices, &actor, &relation_id, mode)
+            .map_err(|e| e.extend())
+    }
+
+    // Stop copying new trades on one of the signed-in follower's relations
+    async fn pause_copying(
+        &self,
+        ctx: &Context<'_>,
+        relation_id: ID,
+    ) -> async_graphql::Result<CopyRelation> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let db = ctx.data_unchecked::<DbPool>();
+        copying::pause(db, &actor, &relation_id).map_err(|e| e.extend())
+    }
+
+    // Copy new trades again on a paused or auto-paused relation
+    async fn resume_copying(
+        &self,
+        ctx: &Context<'_>,
+        relation_id: ID,
+    ) -> async_graphql::Result<CopyRelation> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let db = ctx.data_unchecked::<DbPool>();
+        copying::resume(db, &actor, &relation_id).map_err(|e| e.extend())
+    }
+
+    // Change a relation's sizing, risk or filter settings
+    async fn update_copy_settings(
+        &self,
+        ctx: &Context<'_>,
+        relation_id: ID,
+        input: UpdateCopySettingsInput,
+    ) -> async_graphql::Result<CopyRelation> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let db = ctx.data_unchecked::<DbPool>();
+        copying::update_settings(db, &actor, &relation_id, input).map_err(|e| e.extend())
+    }
+
+    // Close one of the signed-in follower's detached copies at the mark price
+    async fn close_copied_trade(
+        &self,
+        ctx: &Context<'_>,
+        copied_trade_id: ID,
+    ) -> async_graphql::Result<CopiedTrade> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let db = ctx.data_unchecked::<DbPool>();
+        let prices = ctx.data_unchecked::<PricePool>();
+        copying::close_detached_copy(db, prices, &actor, &copied_trade_id).map_err(|e| e.extend())
+    }
+
+    // Place a market, limit or stop order. The trade opens when the order fills.
+    #[graphql(guard = "RoleGuard::new(Role::Trader)")]
+    async fn place_order(
+        &self,
+        ctx: &Context<'_>,
+        mut input: PlaceOrderInput,
+    ) -> async_graphql::Result<Order> {
+        input.trader_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        let db = ctx.data_unchecked::<DbPool>();
+        let prices = ctx.data_unchecked::<PricePool>();
+        let workflows = ctx.data_unchecked::<WorkflowPool>();
+        orders::place_order(db, prices, workflows, input).map_err(|e| e.extend())
+    }
+
+    // Cancel a pending order
+    #[graphql(guard = "RoleGuard::new(Role::Trader)")]
+    async fn cancel_order(&self, ctx: &Context<'_>, order_id: ID) -> async_graphql::Result<Order> {
+        let db = ctx.data_unchecked::<DbPool>();
+        own_order(ctx, db, &order_id).map_err(|e| e.extend())?;
+        orders::cancel_order(db, &order_id).map_err(|e| e.extend())
+    }
+
+    // Amend a pending order's quantity or prices
+    #[graphql(guard = "RoleGuard::new(Role::Trader)")]
+    async fn amend_order(
+        &self,
+        ctx: &Context<'_>,
+        order_id: ID,
+        input: AmendOrderInput,
+    ) -> async_graphql::Result<Order> {
+        let db = ctx.data_unchecked::<DbPool>();
+        own_order(ctx, db, &order_id).map_err(|e| e.extend())?;
+        orders::amend_order(db, &order_id, input).map_err(|e| e.extend())
+    }
+
+    // Make an earlier version of a workflow definition active again
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn rollback_workflow(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        version: u32,
+    ) -> async_graphql::Result<WorkflowDefinition> {
+        ctx.data_unchecked::<WorkflowPool>()
+            .rollback(&name, version)
+            .map_err(|e| e.extend())
+    }
+
+    // Register a new user with a password
+    async fn register_user(
+        &self,
+        ctx: &Context<'_>,
+        username: String,
+        password: String,
+        is_trader: bool,
+    ) -> async_graphql::Result<User> {
+        let db = ctx.data_unchecked::<DbPool>().clone();
+        ctx.data_unchecked::<WorkflowPool>()
+            .register_user(db, &username, &password, is_trader)
+            .map_err(|e| e.extend())
+    }
+
+    // Admin: block a user from signing in and acting
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn suspend_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: ID,
+        reason: String,
+    ) -> async_graphql::Result<User> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        admin::suspend_user(ctx.data_unchecked::<DbPool>(), &actor, &user_id, &reason)
+            .map_err(|e| e.extend())
+    }
+
+    // Admin: lift a suspension
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn reinstate_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: ID,
+        reason: String,
+    ) -> async_graphql::Result<User> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        admin::reinstate_user(ctx.data_unchecked::<DbPool>(), &actor, &user_id, &reason)
+            .map_err(|e| e.extend())
+    }
+
+    // Admin: close any trade and its copies, at the mark price unless given
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn force_close_trade(
+        &self,
+        ctx: &Context<'_>,
+        trade_id: ID,
+        exit_price: Option<f64>,
+        reason: String,
+    ) -> async_graphql::Result<Trade> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        admin::force_close_trade(
+            ctx.data_unchecked::<DbPool>(),
+            ctx.data_unchecked::<PricePool>(),
+            &actor,
+            &trade_id,
+            exit_price,
+            &reason,
+        )
+        .map_err(|e| e.extend())
+    }
+
+    // Admin: deactivate any copy relation
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn disable_copy_relation(
+        &self,
+        ctx: &Context<'_>,
+        relation_id: ID,
+        reason: String,
+    ) -> async_graphql::Result<CopyRelation> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        admin::disable_copy_relation(
+            ctx.data_unchecked::<DbPool>(),
+            &actor,
+            &relation_id,
+            &reason,
+        )
+        .map_err(|e| e.extend())
+    }
+
+    // Admin: credit (positive) or debit (negative) a user's balance
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn adjust_balance(
+        &self,
+        ctx: &Context<'_>,
+        user_id: ID,
+        amount: f64,
+        reason: String,
+    ) -> async_graphql::Result<BalanceEntry> {
+        let actor = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        admin::adjust_balance(
+            ctx.data_unchecked::<DbPool>(),
+            &actor,
+            &user_id,
+            amount,
+            &reason,
+        )
+        .map_err(|e| e.extend())
+    }
+
+    // Sign in and receive a session token for the Authorization header
+    async fn login(
+        &self,
+        ctx: &Context<'_>,
+        username: String,
+        password: String,
+    ) -> async_graphql::Result<AuthPayload> {
+        let db = ctx.data_unchecked::<DbPool>();
+        let keys = ctx.data_unchecked::<web::Data<AuthKeys>>();
+        auth::login(db, keys, &username, &password).map_err(|e| e.extend())
     }
 }
 
+// The trade, if the signed-in user opened it
+fn own_trade(ctx: &Context<'_>, db: &Store, trade_id: &str) -> DomainResult<Trade> {
+    let actor = auth::acting_user(ctx)?;
+    let trade = db
+        .get_trade(trade_id)?
+        .ok_or_else(|| DomainError::not_found("Trade", trade_id))?;
+    auth::ensure_owner(&trade.trader_id, &actor, "Trade")?;
+    Ok(trade)
+}
+
+// The order, if the signed-in user placed it
+fn own_order(ctx: &Context<'_>, db: &Store, order_id: &str) -> DomainResult<Order> {
+    let actor = auth::acting_user(ctx)?;
+    let order = db
+        .get_order(order_id)?
+        .ok_or_else(|| DomainError::not_found("Order", order_id))?;
+    auth::ensure_owner(&order.trader_id, &actor, "Order")?;
+    Ok(order)
+}
+
+pub type AppSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
+
 // ================= HTTP Handlers =================
 
 // GraphQL endpoint
 async fn graphql_handler(
-    schema: web::Data<Schema<QueryRoot, MutationRoot, EmptySubscription>>,
+    schema: web::Data<AppSchema>,
+    http_req: HttpRequest,
     req: GraphQLRequest,
 ) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+    let mut request = req.into_inner();
+    // Set by auth::authenticate for a valid bearer token
+    if let Some(session) = http_req.extensions().get::<Session>().cloned() {
+        request = request.data(session);
+    }
+    schema.execute(request).await.into()
+}
+
+// GraphQL subscriptions over websocket (graphql-ws / graphql-transport-ws).
+// The session comes from the connection_init payload, or from an Authorization
+// header on the upgrade request for clients that can send one.
+async fn graphql_ws_handler(
+    schema: web::Data<AppSchema>,
+    keys: web::Data<AuthKeys>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> actix_web::Result<HttpResponse> {
+    let upgrade_session = req.extensions().get::<Session>().cloned();
+    GraphQLSubscription::new(AppSchema::clone(&schema))
+        .on_connection_init(move |init| async move {
+            let session = auth::connection_session(&keys, &init)
+                .map_err(|e| e.extend())?
+                .or(upgrade_session);
+            let mut data = async_graphql::Data::default();
+            if let Some(session) = session {
+                data.insert(session);
+            }
+            Ok(data)
+        })
+        .start(&req, payload)
 }
 
 // GraphQL Playground
@@ -604,7 +1246,8 @@ async fn graphql_playground() -> HttpResponse {
     HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
         .body(async_graphql::http::playground_source(
-            async_graphql::http::GraphQLPlaygroundConfig::new("/graphql"),
+            async_graphql::http::GraphQLPlaygroundConfig::new("/graphql")
+                .subscription_endpoint("/graphql"),
         ))
 }
 
@@ -613,17 +1256,52 @@ async fn graphql_playground() -> HttpResponse {
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     println!("🚀 CopyTrade Backend + Snurr BPMN");
-    println!("📋 Workflows: bpmn/create_trade.bpmn, bpmn/copy_trader.bpmn");
     println!("📊 Playground: http://localhost:8080/playground\n");
 
-    // Initialize in-memory DB with sample data
-    let mut db = Database::default();
-    init_sample_data(&mut db);
-    let db_pool: DbPool = Arc::new(RwLock::new(db));
+    // Build every BPMN workflow up front; a diagram without handlers stops boot here
+    let bpmn_dir = workflows::dir_from_env();
+    let workflows =
+        WorkflowPool::new(workflows::Workflows::load(&bpmn_dir).map_err(std::io::Error::other)?);
+    // Hot reload is a convenience; keep serving the loaded versions without it
+    if let Err(e) = workflows::spawn_watcher(workflows.clone(), bpmn_dir) {
+        println!("⚠️ {}", e);
+    }
+
+    // Open the configured storage backend and seed it on first run
+    let db_pool = storage::open_from_env().map_err(std::io::Error::other)?;
+    if db_pool.is_empty().map_err(std::io::Error::other)? {
+        seed_from_env(&db_pool).map_err(std::io::Error::other)?;
+    }
+    // Fill in copied trades from logs older than their own fill fields
+    copying::backfill_copies(&db_pool).map_err(std::io::Error::other)?;
+    // Derive trader statistics from the trade history
+    stats::recompute_all(&db_pool).map_err(std::io::Error::other)?;
+
+    // Start the market price simulator
+    let price_feed = PricePool::default();
+    let tick_ms = std::env::var("PRICE_TICK_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1000);
+    prices::spawn_simulator(
+        price_feed.clone(),
+        prices::source_from_env().map_err(std::io::Error::other)?,
+        prices::seeds_from_env().map_err(std::io::Error::other)?,
+        db_pool.clone(),
+        std::time::Duration::from_millis(tick_ms),
+    )
+    .map_err(std::io::Error::other)?;
+    monitor::spawn_level_monitor(db_pool.clone(), price_feed.clone());
+    orders::spawn_order_matcher(db_pool.clone(), price_feed.clone(), workflows.clone());
+
+    let auth_keys = web::Data::new(auth::keys_from_env().map_err(std::io::Error::other)?);
 
     // Build GraphQL schema
-    let schema = Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+    let schema = Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
         .data(db_pool)
+        .data(price_feed)
+        .data(workflows)
+        .data(auth_keys.clone())
         .finish();
 
     // Start HTTP server
@@ -635,13 +1313,15 @@ async fn main() -> std::io::Result<()> {
                     .allow_any_method()
                     .allow_any_header(),
             )
+            .wrap(middleware::from_fn(auth::authenticate))
             .app_data(web::Data::new(schema.clone()))
+            .app_data(auth_keys.clone())
             .route("/graphql", web::post().to(graphql_handler))
             .route(
                 "/graphql",
                 web::get()
                     .guard(guard::Header("upgrade", "websocket"))
-                    .to(graphql_handler),
+                    .to(graphql_ws_handler),
             )
             .route("/playground", web::get().to(graphql_playground))
     })
@@ -0,0 +1,78 @@
+use crate::prices::{PricePool, PriceQuote};
+use crate::storage::{DbPool, Store};
+use crate::{TradeStatus, trading};
+use tokio::sync::broadcast::error::RecvError;
+
+// ================= Stop Loss / Take Profit Monitor =================
+
+// Evaluate protective levels against every price tick
+pub fn spawn_level_monitor(db: DbPool, prices: PricePool) {
+    let mut ticks = prices.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let quote = match ticks.recv().await {
+                Ok(quote) => quote,
+                Err(RecvError::Lagged(skipped)) => {
+                    println!("⚠️ Level monitor lagged, skipped {} ticks", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let db = db.clone();
+            // Storage calls block (SQLite, fsync), keep them off the async workers
+            let checked = tokio::task::spawn_blocking(move || check_levels(&db, &quote)).await;
+            if let Ok(Err(e)) = checked {
+                println!("⚠️ Level monitor error: {}", e);
+            }
+        }
+    });
+}
+
+fn check_levels(db: &Store, quote: &PriceQuote) -> Result<(), String> {
+    // Trader levels: closing the trade also closes every copy of it
+    for trade in db.list_trades()? {
+        if trade.status != TradeStatus::Open || trade.symbol != quote.symbol {
+            continue;
+        }
+        if let Some(reason) = trading::triggered(
+            trade.direction,
+            trade.stop_loss,
+            trade.take_profit,
+            quote.price,
+        ) {
+            println!(
+                "🎯 {:?} hit on {} @ {:.2}",
+                reason,
+                trade.id.as_str(),
+                quote.price
+            );
+            if let Err(e) = trading::close_trade(db, &trade.id, quote.price, reason) {
+                println!("⚠️ Could not close {}: {}", trade.id.as_str(), e);
+            }
+        }
+    }
+
+    // Follower overrides: close only the follower's copy
+    for ct in db.list_copied_trades()? {
+        if ct.status != TradeStatus::Open
+            || ct.symbol != quote.symbol
+            || (ct.stop_loss.is_none() && ct.take_profit.is_none())
+        {
+            continue;
+        }
+        if let Some(reason) =
+            trading::triggered(ct.direction, ct.stop_loss, ct.take_profit, quote.price)
+        {
+            println!(
+                "🎯 {:?} hit on copy {} @ {:.2}",
+                reason,
+                ct.id.as_str(),
+                quote.price
+            );
+            if let Err(e) = trading::close_copied_trade(db, &ct.id, quote.price, reason) {
+                println!("⚠️ Could not close copy {}: {}", ct.id.as_str(), e);
+            }
+        }
+    }
+    Ok(())
+}
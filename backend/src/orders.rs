@@ -0,0 +1,375 @@
+use crate::errors::{DomainError, DomainResult};
+use crate::events::DomainEvent;
+use crate::prices::{PricePool, PriceQuote};
+use crate::storage::{DbPool, Store};
+use crate::workflows::{self, WorkflowPool};
+use crate::{CreateTradeInput, TradeDirection, trading};
+use async_graphql::{Enum, ID, InputObject, SimpleObject};
+use chrono::{DateTime, Days, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+// ================= Order Models =================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum OrderType {
+    Market,
+    Limit,
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum TimeInForce {
+    // Good till cancelled
+    Gtc,
+    // Immediate or cancel
+    Ioc,
+    // Expires at the end of the UTC day it was placed
+    Day,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum OrderStatus {
+    Pending,
+    Filled,
+    Cancelled,
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+pub struct Order {
+    pub id: ID,
+    pub trader_id: ID,
+    pub symbol: String,
+    pub direction: TradeDirection,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    pub quantity: f64,
+    pub limit_price: Option<f64>,
+    pub stop_price: Option<f64>,
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+    pub status: OrderStatus,
+    pub fill_price: Option<f64>,
+    pub trade_id: Option<ID>,
+    // Why the order was cancelled when the system (not the trader) did it
+    pub reason: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(InputObject)]
+pub struct PlaceOrderInput {
+    // The signed-in trader; set by the server
+    #[graphql(skip)]
+    pub trader_id: ID,
+    pub symbol: String,
+    pub direction: TradeDirection,
+    pub order_type: OrderType,
+    #[graphql(default_with = "TimeInForce::Gtc")]
+    pub time_in_force: TimeInForce,
+    pub quantity: f64,
+    pub limit_price: Option<f64>,
+    pub stop_price: Option<f64>,
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+}
+
+// Fields left out keep their current value
+#[derive(InputObject)]
+pub struct AmendOrderInput {
+    pub quantity: Option<f64>,
+    pub limit_price: Option<f64>,
+    pub stop_price: Option<f64>,
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+}
+
+// ================= Order Lifecycle =================
+
+// Place an order. Market and fillable IOC orders fill right away against the
+// current mark price; everything else waits for the matcher.
+pub fn place_order(
+    db: &DbPool,
+    prices: &PricePool,
+    workflows: &WorkflowPool,
+    input: PlaceOrderInput,
+) -> DomainResult<Order> {
+    let now = Utc::now();
+    let order = Order {
+        id: ID(Uuid::new_v4().to_string()),
+        trader_id: input.trader_id,
+        symbol: input.symbol,
+        direction: input.direction,
+        order_type: input.order_type,
+        time_in_force: input.time_in_force,
+        quantity: input.quantity,
+        limit_price: input.limit_price,
+        stop_price: input.stop_price,
+        stop_loss: input.stop_loss,
+        take_profit: input.take_profit,
+        status: OrderStatus::Pending,
+        fill_price: None,
+        trade_id: None,
+        reason: None,
+        expires_at: match input.time_in_force {
+            TimeInForce::Day => end_of_day(now),
+            TimeInForce::Gtc | TimeInForce::Ioc => None,
+        },
+        created_at: now,
+        updated_at: now,
+    };
+    validate(&order)?;
+    workflows::require_trader(db, &order.trader_id)?;
+    // Orders on a symbol nobody quotes would never fill
+    let mark = prices
+        .price(&order.symbol)
+        .ok_or_else(|| DomainError::conflict(format!("No market price for {}", order.symbol)))?;
+    db.record(DomainEvent::OrderPlaced {
+        order: order.clone(),
+    })?;
+    println!(
+        "📝 Order placed: {} {:?}",
+        order.id.as_str(),
+        order.order_type
+    );
+
+    if fillable(&order, mark) {
+        fill_order(db, workflows, &order.id, mark)
+    } else if order.time_in_force == TimeInForce::Ioc {
+        cancel(
+            db,
+            &order.id,
+            OrderStatus::Cancelled,
+            Some("Not immediately fillable"),
+        )
+    } else {
+        Ok(order)
+    }
+}
+
+// Cancel a pending order on the trader's request
+pub fn cancel_order(db: &DbPool, order_id: &str) -> DomainResult<Order> {
+    cancel(db, order_id, OrderStatus::Cancelled, None)
+}
+
+// Change a pending order's size or prices
+pub fn amend_order(db: &DbPool, order_id: &str, input: AmendOrderInput) -> DomainResult<Order> {
+    let _orders = db.lock_orders();
+    let mut order = pending_order(db, order_id)?;
+    if let Some(quantity) = input.quantity {
+        order.quantity = quantity;
+    }
+    if input.limit_price.is_some() {
+        order.limit_price = input.limit_price;
+    }
+    if input.stop_price.is_some() {
+        order.stop_price = input.stop_price;
+    }
+    if input.stop_loss.is_some() {
+        order.stop_loss = input.stop_loss;
+    }
+    if input.take_profit.is_some() {
+        order.take_profit = input.take_profit;
+    }
+    validate(&order)?;
+    order.updated_at = Utc::now();
+    db.record(DomainEvent::OrderAmended {
+        order: order.clone(),
+    })?;
+    Ok(order)
+}
+
+// Cancel every pending order of a trader, e.g. once they are suspended
+pub fn cancel_pending_orders(db: &Store, trader_id: &str, reason: &str) -> DomainResult<usize> {
+    let mut cancelled = 0;
+    for order in db.orders_by_trader(trader_id)? {
+        if order.status != OrderStatus::Pending {
+            continue;
+        }
+        match cancel(db, &order.id, OrderStatus::Cancelled, Some(reason)) {
+            Ok(_) => cancelled += 1,
+            // Filled or cancelled since it was listed
+            Err(DomainError::Conflict { .. }) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(cancelled)
+}
+
+fn cancel(
+    db: &Store,
+    order_id: &str,
+    status: OrderStatus,
+    reason: Option<&str>,
+) -> DomainResult<Order> {
+    let _orders = db.lock_orders();
+    let mut order = pending_order(db, order_id)?;
+    order.status = status;
+    order.reason = reason.map(str::to_string);
+    order.updated_at = Utc::now();
+    let event = match status {
+        OrderStatus::Expired => DomainEvent::OrderExpired {
+            order: order.clone(),
+        },
+        _ => DomainEvent::OrderCancelled {
+            order: order.clone(),
+        },
+    };
+    db.record(event)?;
+    println!("🚫 Order {:?}: {}", status, order.id.as_str());
+    Ok(order)
+}
+
+// Fill a pending order at `price`: the Create Trade workflow opens the trade
+// and copies it to followers. A rejected workflow cancels the order.
+fn fill_order(
+    db: &DbPool,
+    workflows: &WorkflowPool,
+    order_id: &str,
+    price: f64,
+) -> DomainResult<Order> {
+    let _orders = db.lock_orders();
+    let mut order = pending_order(db, order_id)?;
+    let input = CreateTradeInput {
+        trader_id: order.trader_id.clone(),
+        symbol: order.symbol.clone(),
+        direction: order.direction,
+        entry_price: price,
+        quantity: order.quantity,
+        stop_loss: order.stop_loss,
+        take_profit: order.take_profit,
+    };
+    order.updated_at = Utc::now();
+    match workflows.create_trade(db.clone(), &input) {
+        Ok(trade) => {
+            order.status = OrderStatus::Filled;
+            order.fill_price = Some(price);
+            order.trade_id = Some(trade.id);
+            db.record(DomainEvent::OrderFilled {
+                order: order.clone(),
+            })?;
+            println!("✅ Order filled: {} @ {:.2}", order.id.as_str(), price);
+        }
+        Err(e) => {
+            order.status = OrderStatus::Cancelled;
+            order.reason = Some(e.to_string());
+            db.record(DomainEvent::OrderCancelled {
+                order: order.clone(),
+            })?;
+            println!("🚫 Order rejected: {}", order.id.as_str());
+        }
+    }
+    Ok(order)
+}
+
+fn pending_order(db: &Store, order_id: &str) -> DomainResult<Order> {
+    let order = db
+        .get_order(order_id)?
+        .ok_or_else(|| DomainError::not_found("Order", order_id))?;
+    if order.status != OrderStatus::Pending {
+        return Err(DomainError::conflict(format!(
+            "Order is {:?}",
+            order.status
+        )));
+    }
+    Ok(order)
+}
+
+fn validate(order: &Order) -> DomainResult<()> {
+    let positive = |price: Option<f64>| price.is_some_and(|p| p > 0.0);
+    if !trading::valid_symbol(&order.symbol) {
+        return Err(DomainError::invalid("symbol", "Invalid symbol"));
+    }
+    if order.quantity <= 0.0 {
+        return Err(DomainError::invalid("quantity", "Invalid quantity"));
+    }
+    match order.order_type {
+        OrderType::Market if order.limit_price.is_some() || order.stop_price.is_some() => Err(
+            DomainError::invalid("orderType", "Market orders take no limit or stop price"),
+        ),
+        OrderType::Limit if !positive(order.limit_price) => {
+            Err(DomainError::invalid("limitPrice", "Invalid limit price"))
+        }
+        OrderType::Stop if !positive(order.stop_price) => {
+            Err(DomainError::invalid("stopPrice", "Invalid stop price"))
+        }
+        _ => {
+            // Market orders check their levels against the fill price in the workflow
+            match order.limit_price.or(order.stop_price) {
+                Some(reference) => trading::validate_levels(
+                    order.direction,
+                    reference,
+                    order.stop_loss,
+                    order.take_profit,
+                ),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+// Whether the order would execute at `price`
+fn fillable(order: &Order, price: f64) -> bool {
+    match (order.order_type, order.direction) {
+        (OrderType::Market, _) => true,
+        // Buy at or below the limit, sell at or above it
+        (OrderType::Limit, TradeDirection::Long) => order.limit_price.is_some_and(|l| price <= l),
+        (OrderType::Limit, TradeDirection::Short) => order.limit_price.is_some_and(|l| price >= l),
+        // Stops trigger once the market moves through them
+        (OrderType::Stop, TradeDirection::Long) => order.stop_price.is_some_and(|s| price >= s),
+        (OrderType::Stop, TradeDirection::Short) => order.stop_price.is_some_and(|s| price <= s),
+    }
+}
+
+fn end_of_day(now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    now.date_naive()
+        .checked_add_days(Days::new(1))?
+        .and_hms_opt(0, 0, 0)
+        .map(|midnight| midnight.and_utc())
+}
+
+// ================= Order Matcher =================
+
+// Fill or expire pending orders as prices move
+pub fn spawn_order_matcher(db: DbPool, prices: PricePool, workflows: WorkflowPool) {
+    let mut ticks = prices.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let quote = match ticks.recv().await {
+                Ok(quote) => quote,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let db = db.clone();
+            let workflows = workflows.clone();
+            let matched =
+                tokio::task::spawn_blocking(move || match_orders(&db, &workflows, &quote)).await;
+            if let Ok(Err(e)) = matched {
+                println!("⚠️ Order matcher error: {}", e);
+            }
+        }
+    });
+}
+
+fn match_orders(db: &DbPool, workflows: &WorkflowPool, quote: &PriceQuote) -> DomainResult<()> {
+    let now = Utc::now();
+    for order in db.list_orders()? {
+        if order.status != OrderStatus::Pending {
+            continue;
+        }
+        let result = if order.expires_at.is_some_and(|at| at <= now) {
+            cancel(db, &order.id, OrderStatus::Expired, Some("End of day"))
+        } else if order.symbol == quote.symbol && fillable(&order, quote.price) {
+            fill_order(db, workflows, &order.id, quote.price)
+        } else {
+            continue;
+        };
+        if let Err(e) = result {
+            println!("⚠️ Could not process order {}: {}", order.id.as_str(), e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,531 @@
+use crate::copying::CopyStatus;
+use crate::{CopiedTrade, CopyRelation, Trade, TradeDirection, TradeStatus, User};
+use async_graphql::connection::{self, Connection, CursorType, Edge};
+use async_graphql::{Enum, ID, InputObject, OutputType, SimpleObject};
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+
+// ================= Connections =================
+
+// Extra fields on every connection
+#[derive(SimpleObject)]
+pub struct ListTotals {
+    // Items matching the filter, across all pages
+    pub total_count: usize,
+}
+
+pub type ListConnection<T> = Connection<Position, T, ListTotals>;
+
+// Where an item sits in a sorted list: its sort key, then its id to break ties.
+// Cursors carry the position rather than an index, so a page boundary stays put
+// when items are added or removed between requests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    key: SortValue,
+    id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortValue {
+    // Nanoseconds since the epoch
+    Time(i64),
+    Number(f64),
+}
+
+impl Position {
+    pub fn time(at: DateTime<Utc>, id: &ID) -> Self {
+        Position {
+            key: SortValue::Time(at.timestamp_nanos_opt().unwrap_or(i64::MAX)),
+            id: id.to_string(),
+        }
+    }
+
+    pub fn number(value: f64, id: &ID) -> Self {
+        Position {
+            key: SortValue::Number(value),
+            id: id.to_string(),
+        }
+    }
+
+    fn cmp(&self, other: &Position) -> Ordering {
+        let key = match (self.key, other.key) {
+            (SortValue::Time(a), SortValue::Time(b)) => a.cmp(&b),
+            (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(&b),
+            // A cursor from another sort key; order it consistently anyway
+            (SortValue::Time(_), SortValue::Number(_)) => Ordering::Less,
+            (SortValue::Number(_), SortValue::Time(_)) => Ordering::Greater,
+        };
+        key.then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+// Cursors read `t:<nanos>|<id>` or `n:<number>|<id>`
+impl CursorType for Position {
+    type Error = String;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        let invalid = || format!("Invalid cursor: {}", s);
+        let (key, id) = s.split_once('|').ok_or_else(invalid)?;
+        let key = match key.split_once(':') {
+            Some(("t", nanos)) => SortValue::Time(nanos.parse().map_err(|_| invalid())?),
+            Some(("n", value)) => SortValue::Number(value.parse().map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        };
+        Ok(Position {
+            key,
+            id: id.to_string(),
+        })
+    }
+
+    fn encode_cursor(&self) -> String {
+        match self.key {
+            SortValue::Time(nanos) => format!("t:{}|{}", nanos, self.id),
+            SortValue::Number(value) => format!("n:{}|{}", value, self.id),
+        }
+    }
+}
+
+// Sort order of a list query: each item's position and the direction
+pub trait ListSort<T> {
+    fn position(&self, item: &T) -> Position;
+    fn direction(&self) -> SortDirection;
+}
+
+// Sort `items` and page through them Relay-style. `after` and `before` seek to
+// the first item past the cursor's position. Without `first` or `last` the
+// whole list is returned.
+pub async fn paginate<T: OutputType, S: ListSort<T>>(
+    items: Vec<T>,
+    sort: &S,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+) -> async_graphql::Result<ListConnection<T>> {
+    let direction = sort.direction();
+    let mut items: Vec<(Position, T)> = items
+        .into_iter()
+        .map(|item| (sort.position(&item), item))
+        .collect();
+    items.sort_by(|(a, _), (b, _)| direction.apply(a.cmp(b)));
+    connection::query(
+        after,
+        before,
+        first,
+        last,
+        |after: Option<Position>, before: Option<Position>, first, last| async move {
+            let total = items.len();
+            let mut end = before.map_or(total, |before| {
+                items.partition_point(|(p, _)| direction.apply(p.cmp(&before)) == Ordering::Less)
+            });
+            let mut start = after
+                .map_or(0, |after| {
+                    items.partition_point(|(p, _)| {
+                        direction.apply(p.cmp(&after)) != Ordering::Greater
+                    })
+                })
+                .min(end);
+            if let Some(first) = first {
+                end = end.min(start + first);
+            }
+            if let Some(last) = last {
+                start = start.max(end.saturating_sub(last));
+            }
+            let mut connection = Connection::with_additional_fields(
+                start > 0,
+                end < total,
+                ListTotals { total_count: total },
+            );
+            connection.edges.extend(
+                items
+                    .into_iter()
+                    .skip(start)
+                    .take(end - start)
+                    .map(|(position, item)| Edge::new(position, item)),
+            );
+            Ok::<_, async_graphql::Error>(connection)
+        },
+    )
+    .await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    fn apply(self, ordering: Ordering) -> Ordering {
+        match self {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
+        }
+    }
+}
+
+// `from` inclusive, `to` exclusive
+fn in_range(at: DateTime<Utc>, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> bool {
+    from.is_none_or(|from| at >= from) && to.is_none_or(|to| at < to)
+}
+
+// ================= Users =================
+
+#[derive(Default, InputObject)]
+pub struct UserFilter {
+    pub is_trader: Option<bool>,
+    // Case-insensitive substring of the username
+    pub username: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+}
+
+impl UserFilter {
+    pub fn matches(&self, user: &User) -> bool {
+        self.is_trader.is_none_or(|t| user.is_trader == t)
+            && self
+                .username
+                .as_ref()
+                .is_none_or(|name| user.username.to_lowercase().contains(&name.to_lowercase()))
+            && in_range(user.created_at, self.created_from, self.created_to)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum UserSortKey {
+    CreatedAt,
+    Pnl,
+    FollowersCount,
+}
+
+#[derive(InputObject)]
+pub struct UserSort {
+    pub key: UserSortKey,
+    #[graphql(default_with = "SortDirection::Desc")]
+    pub direction: SortDirection,
+}
+
+impl Default for UserSort {
+    fn default() -> Self {
+        UserSort {
+            key: UserSortKey::CreatedAt,
+            direction: SortDirection::Desc,
+        }
+    }
+}
+
+impl ListSort<User> for UserSort {
+    fn position(&self, user: &User) -> Position {
+        match self.key {
+            UserSortKey::CreatedAt => Position::time(user.created_at, &user.id),
+            UserSortKey::Pnl => Position::number(user.total_pnl, &user.id),
+            UserSortKey::FollowersCount => Position::number(user.followers_count as f64, &user.id),
+        }
+    }
+
+    fn direction(&self) -> SortDirection {
+        self.direction
+    }
+}
+
+// ================= Trades =================
+
+// Matches trades, and copied trades by their own fields
+#[derive(Default, InputObject)]
+pub struct TradeFilter {
+    pub trader_id: Option<ID>,
+    pub symbol: Option<String>,
+    pub direction: Option<TradeDirection>,
+    pub status: Option<TradeStatus>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+}
+
+impl TradeFilter {
+    pub fn matches(&self, trade: &Trade) -> bool {
+        self.matches_fields(
+            Some(&trade.trader_id),
+            &trade.symbol,
+            trade.direction,
+            trade.status,
+            trade.created_at,
+        )
+    }
+
+    // `trader_id` is the trader of the copy's relation, when known
+    pub fn matches_copy(&self, copied_trade: &CopiedTrade, trader_id: Option<&ID>) -> bool {
+        self.matches_fields(
+            trader_id,
+            &copied_trade.symbol,
+            copied_trade.direction,
+            copied_trade.status,
+            copied_trade.opened_at,
+        )
+    }
+
+    fn matches_fields(
+        &self,
+        trader_id: Option<&ID>,
+        symbol: &str,
+        direction: TradeDirection,
+        status: TradeStatus,
+        created_at: DateTime<Utc>,
+    ) -> bool {
+        self.trader_id
+            .as_ref()
+            .is_none_or(|id| trader_id == Some(id))
+            && self.symbol.as_ref().is_none_or(|s| symbol == s)
+            && self.direction.is_none_or(|d| direction == d)
+            && self.status.is_none_or(|s| status == s)
+            && in_range(created_at, self.created_from, self.created_to)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum TradeSortKey {
+    CreatedAt,
+    Pnl,
+}
+
+#[derive(InputObject)]
+pub struct TradeSort {
+    pub key: TradeSortKey,
+    #[graphql(default_with = "SortDirection::Desc")]
+    pub direction: SortDirection,
+}
+
+impl Default for TradeSort {
+    fn default() -> Self {
+        TradeSort {
+            key: TradeSortKey::CreatedAt,
+            direction: SortDirection::Desc,
+        }
+    }
+}
+
+impl ListSort<Trade> for TradeSort {
+    fn position(&self, trade: &Trade) -> Position {
+        match self.key {
+            TradeSortKey::CreatedAt => Position::time(trade.created_at, &trade.id),
+            TradeSortKey::Pnl => {
+                Position::number(booked_pnl(trade.pnl, trade.realized_pnl), &trade.id)
+            }
+        }
+    }
+
+    fn direction(&self) -> SortDirection {
+        self.direction
+    }
+}
+
+// Copies sort by their own open time and PnL
+impl ListSort<CopiedTrade> for TradeSort {
+    fn position(&self, copied_trade: &CopiedTrade) -> Position {
+        match self.key {
+            TradeSortKey::CreatedAt => Position::time(copied_trade.opened_at, &copied_trade.id),
+            TradeSortKey::Pnl => Position::number(
+                booked_pnl(copied_trade.pnl, copied_trade.realized_pnl),
+                &copied_trade.id,
+            ),
+        }
+    }
+
+    fn direction(&self) -> SortDirection {
+        self.direction
+    }
+}
+
+// Final PnL once closed, otherwise what partial closes have booked so far
+fn booked_pnl(pnl: Option<f64>, realized_pnl: f64) -> f64 {
+    pnl.unwrap_or(realized_pnl)
+}
+
+// ================= Copy Relations =================
+
+#[derive(Default, InputObject)]
+pub struct CopyRelationFilter {
+    pub trader_id: Option<ID>,
+    // Relations not yet stopped unless set
+    pub status: Option<CopyStatus>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+}
+
+impl CopyRelationFilter {
+    pub fn matches(&self, relation: &CopyRelation) -> bool {
+        self.trader_id
+            .as_ref()
+            .is_none_or(|id| relation.trader_id == *id)
+            && self
+                .status
+                .map_or(relation.status.is_following(), |s| relation.status == s)
+            && in_range(relation.created_at, self.created_from, self.created_to)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum CopyRelationSortKey {
+    CreatedAt,
+}
+
+#[derive(InputObject)]
+pub struct CopyRelationSort {
+    pub key: CopyRelationSortKey,
+    #[graphql(default_with = "SortDirection::Desc")]
+    pub direction: SortDirection,
+}
+
+impl Default for CopyRelationSort {
+    fn default() -> Self {
+        CopyRelationSort {
+            key: CopyRelationSortKey::CreatedAt,
+            direction: SortDirection::Desc,
+        }
+    }
+}
+
+impl ListSort<CopyRelation> for CopyRelationSort {
+    fn position(&self, relation: &CopyRelation) -> Position {
+        match self.key {
+            CopyRelationSortKey::CreatedAt => Position::time(relation.created_at, &relation.id),
+        }
+    }
+
+    fn direction(&self) -> SortDirection {
+        self.direction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Items are their own id; the sort key is the value divided by ten, so
+    // values in the same ten tie and fall back to the id
+    struct TensSort(SortDirection);
+
+    impl ListSort<i32> for TensSort {
+        fn position(&self, item: &i32) -> Position {
+            Position::number((item / 10) as f64, &ID(format!("{:03}", item)))
+        }
+
+        fn direction(&self) -> SortDirection {
+            self.0
+        }
+    }
+
+    async fn page(
+        items: &[i32],
+        direction: SortDirection,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> ListConnection<i32> {
+        paginate(
+            items.to_vec(),
+            &TensSort(direction),
+            after,
+            before,
+            first,
+            last,
+        )
+        .await
+        .unwrap()
+    }
+
+    fn nodes(connection: &ListConnection<i32>) -> Vec<i32> {
+        connection.edges.iter().map(|e| e.node).collect()
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let at = DateTime::from_timestamp(1_700_000_000, 123).unwrap();
+        let time = Position::time(at, &ID("a|b".into()));
+        assert_eq!(time.encode_cursor(), "t:1700000000000000123|a|b");
+        assert_eq!(Position::decode_cursor(&time.encode_cursor()), Ok(time));
+
+        let number = Position::number(-2.5, &ID("x".into()));
+        assert_eq!(number.encode_cursor(), "n:-2.5|x");
+        assert_eq!(Position::decode_cursor(&number.encode_cursor()), Ok(number));
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        for cursor in ["", "t:12", "x:1|id", "t:abc|id", "n:|id", "12|id"] {
+            assert!(Position::decode_cursor(cursor).is_err(), "{}", cursor);
+        }
+    }
+
+    #[test]
+    fn ties_break_on_id() {
+        let a = Position::number(1.0, &ID("a".into()));
+        let b = Position::number(1.0, &ID("b".into()));
+        let higher = Position::number(2.0, &ID("a".into()));
+        assert_eq!(a.cmp(&b), Ordering::Less);
+        assert_eq!(b.cmp(&higher), Ordering::Less);
+        assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+    }
+
+    #[tokio::test]
+    async fn pages_forward_and_backward() {
+        let items = [31, 5, 12, 18, 7, 25];
+        let first = page(&items, SortDirection::Asc, None, None, Some(2), None).await;
+        assert_eq!(nodes(&first), [5, 7]);
+        assert!(!first.has_previous_page && first.has_next_page);
+        assert_eq!(first.additional_fields.total_count, 6);
+
+        let cursor = first.edges.last().unwrap().cursor.encode_cursor();
+        let second = page(
+            &items,
+            SortDirection::Asc,
+            Some(cursor),
+            None,
+            Some(3),
+            None,
+        )
+        .await;
+        assert_eq!(nodes(&second), [12, 18, 25]);
+        assert!(second.has_previous_page && second.has_next_page);
+
+        let cursor = second.edges[0].cursor.encode_cursor();
+        let back = page(
+            &items,
+            SortDirection::Asc,
+            None,
+            Some(cursor),
+            None,
+            Some(1),
+        )
+        .await;
+        assert_eq!(nodes(&back), [7]);
+
+        let desc = page(&items, SortDirection::Desc, None, None, Some(3), None).await;
+        assert_eq!(nodes(&desc), [31, 25, 18]);
+    }
+
+    #[tokio::test]
+    async fn seeking_survives_removed_items() {
+        let items = [1, 2, 3, 4, 5];
+        let first = page(&items, SortDirection::Asc, None, None, Some(3), None).await;
+        let cursor = first.edges.last().unwrap().cursor.encode_cursor();
+        // The item the cursor points at is gone; the next page starts after it
+        let rest = page(
+            &[1, 2, 4, 5],
+            SortDirection::Asc,
+            Some(cursor),
+            None,
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(nodes(&rest), [4, 5]);
+    }
+
+    #[tokio::test]
+    async fn no_limit_returns_everything() {
+        let all = page(&[3, 1, 2], SortDirection::Asc, None, None, None, None).await;
+        assert_eq!(nodes(&all), [1, 2, 3]);
+        assert!(!all.has_previous_page && !all.has_next_page);
+    }
+}
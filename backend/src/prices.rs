@@ -0,0 +1,257 @@
+use crate::TradeStatus;
+use crate::storage::{DbPool, StorageResult};
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+// ================= Price Sources =================
+
+// Produces market prices, one step at a time
+pub trait PriceSource: Send {
+    // Advance the market one step and return the new (symbol, price) pairs
+    fn next_tick(&mut self) -> Vec<(String, f64)>;
+
+    // Start quoting `symbol` from `initial_price` if the source does not know it yet
+    fn track(&mut self, _symbol: &str, _initial_price: f64) {}
+}
+
+// Seeded random walk per symbol. The same seed always yields the same path.
+pub struct RandomWalkSource {
+    seed: u64,
+    volatility: f64,
+    // BTreeMap keeps tick order stable across runs
+    symbols: BTreeMap<String, (f64, u64)>,
+}
+
+impl RandomWalkSource {
+    pub fn new(seed: u64, volatility: f64) -> Self {
+        RandomWalkSource {
+            seed,
+            volatility,
+            symbols: BTreeMap::new(),
+        }
+    }
+}
+
+impl PriceSource for RandomWalkSource {
+    fn next_tick(&mut self) -> Vec<(String, f64)> {
+        self.symbols
+            .iter_mut()
+            .map(|(symbol, (price, rng))| {
+                // xorshift64: cheap, deterministic, good enough for a simulator
+                *rng ^= *rng << 13;
+                *rng ^= *rng >> 7;
+                *rng ^= *rng << 17;
+                let unit = (*rng >> 11) as f64 / (1u64 << 53) as f64;
+                *price *= 1.0 + self.volatility * (unit * 2.0 - 1.0);
+                (symbol.clone(), *price)
+            })
+            .collect()
+    }
+
+    fn track(&mut self, symbol: &str, initial_price: f64) {
+        let seed = self.seed;
+        self.symbols
+            .entry(symbol.to_string())
+            .or_insert_with(|| (initial_price, symbol_seed(seed, symbol)));
+    }
+}
+
+// FNV-1a of the symbol mixed with the global seed (never zero, xorshift needs that)
+fn symbol_seed(seed: u64, symbol: &str) -> u64 {
+    let hash = symbol.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    (hash ^ seed).max(1)
+}
+
+// Replays recorded prices from a CSV file with the header `step,symbol,price`.
+// Each tick emits every row of the next step; the file loops when exhausted.
+pub struct CsvReplaySource {
+    steps: Vec<Vec<(String, f64)>>,
+    next: usize,
+}
+
+impl CsvReplaySource {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let data =
+            std::fs::read_to_string(path).map_err(|e| format!("Price CSV read error: {}", e))?;
+        let mut steps: BTreeMap<u64, Vec<(String, f64)>> = BTreeMap::new();
+        for (line_no, line) in data.lines().enumerate().skip(1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let parsed = match fields.as_slice() {
+                [step, symbol, price] => step
+                    .parse::<u64>()
+                    .ok()
+                    .zip(price.parse::<f64>().ok())
+                    .map(|(step, price)| (step, symbol.to_string(), price)),
+                _ => None,
+            };
+            let (step, symbol, price) =
+                parsed.ok_or_else(|| format!("Price CSV line {} is invalid", line_no + 1))?;
+            steps.entry(step).or_default().push((symbol, price));
+        }
+        if steps.is_empty() {
+            return Err(format!("Price CSV {} has no rows", path));
+        }
+        Ok(CsvReplaySource {
+            steps: steps.into_values().collect(),
+            next: 0,
+        })
+    }
+}
+
+impl PriceSource for CsvReplaySource {
+    fn next_tick(&mut self) -> Vec<(String, f64)> {
+        let tick = self.steps[self.next].clone();
+        self.next = (self.next + 1) % self.steps.len();
+        tick
+    }
+}
+
+// Pick the price source from PRICE_SOURCE (random | csv).
+// random: PRICE_SEED (default 42), PRICE_VOLATILITY per tick (default 0.002)
+// csv: PRICE_CSV path
+pub fn source_from_env() -> Result<Box<dyn PriceSource>, String> {
+    let kind = std::env::var("PRICE_SOURCE").unwrap_or_else(|_| "random".to_string());
+    match kind.as_str() {
+        "random" => {
+            let seed = env_or("PRICE_SEED", 42u64)?;
+            let volatility = env_or("PRICE_VOLATILITY", 0.002f64)?;
+            println!("📈 Prices: random walk (seed {})", seed);
+            Ok(Box::new(RandomWalkSource::new(seed, volatility)))
+        }
+        "csv" => {
+            let path =
+                std::env::var("PRICE_CSV").map_err(|_| "PRICE_CSV is not set".to_string())?;
+            println!("📈 Prices: CSV replay ({})", path);
+            Ok(Box::new(CsvReplaySource::open(&path)?))
+        }
+        other => Err(format!("Unknown PRICE_SOURCE: {}", other)),
+    }
+}
+
+// Starting prices from PRICE_SEEDS ("BTC/USD=50000,ETH/USD=3000"), so market
+// orders work on symbols nobody has traded yet
+const DEFAULT_SEEDS: &str = "BTC/USD=50000,ETH/USD=3000,SOL/USD=150,EUR/USD=1.08,XAU/USD=2300";
+
+pub fn seeds_from_env() -> Result<Vec<(String, f64)>, String> {
+    let seeds = std::env::var("PRICE_SEEDS").unwrap_or_else(|_| DEFAULT_SEEDS.to_string());
+    seeds
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .split_once('=')
+                .and_then(|(symbol, price)| {
+                    let price = price.trim().parse::<f64>().ok()?;
+                    let symbol = symbol.trim();
+                    (crate::trading::valid_symbol(symbol) && price > 0.0)
+                        .then(|| (symbol.to_string(), price))
+                })
+                .ok_or_else(|| format!("Invalid PRICE_SEEDS entry: {}", entry))
+        })
+        .collect()
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> Result<T, String> {
+    match std::env::var(name) {
+        Ok(v) => v.parse().map_err(|_| format!("Invalid {}: {}", name, v)),
+        Err(_) => Ok(default),
+    }
+}
+
+// ================= Price Feed =================
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct PriceQuote {
+    pub symbol: String,
+    pub price: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Latest price per symbol, shared by resolvers, plus a stream of every tick
+pub struct PriceFeed {
+    quotes: RwLock<HashMap<String, PriceQuote>>,
+    ticks: broadcast::Sender<PriceQuote>,
+}
+
+impl Default for PriceFeed {
+    fn default() -> Self {
+        PriceFeed {
+            quotes: RwLock::new(HashMap::new()),
+            ticks: broadcast::channel(1024).0,
+        }
+    }
+}
+
+impl PriceFeed {
+    pub fn quote(&self, symbol: &str) -> Option<PriceQuote> {
+        self.quotes.read().get(symbol).cloned()
+    }
+
+    pub fn price(&self, symbol: &str) -> Option<f64> {
+        self.quotes.read().get(symbol).map(|q| q.price)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PriceQuote> {
+        self.ticks.subscribe()
+    }
+
+    fn publish(&self, symbol: String, price: f64) {
+        let quote = PriceQuote {
+            symbol: symbol.clone(),
+            price,
+            updated_at: Utc::now(),
+        };
+        self.quotes.write().insert(symbol, quote.clone());
+        let _ = self.ticks.send(quote);
+    }
+}
+
+pub type PricePool = Arc<PriceFeed>;
+
+// Drive `source` every `interval`, publishing into `feed`. Symbols in `seeds`
+// are quoted from their reference price right away; other symbols only once the
+// source itself quotes them (e.g. a CSV replay). No trader-supplied price ever
+// sets the market, and trades and orders on unquoted symbols are rejected.
+pub fn spawn_simulator(
+    feed: PricePool,
+    mut source: Box<dyn PriceSource>,
+    seeds: Vec<(String, f64)>,
+    db: DbPool,
+    interval: Duration,
+) -> StorageResult<()> {
+    for (symbol, price) in seeds {
+        source.track(&symbol, price);
+        feed.publish(symbol, price);
+    }
+    // Trades opened before quotes were limited to seeded symbols
+    for trade in db.list_trades()? {
+        if trade.status == TradeStatus::Open && feed.price(&trade.symbol).is_none() {
+            println!(
+                "⚠️ Open trade {} on {} has no reference price; add it to PRICE_SEEDS",
+                trade.id.as_str(),
+                trade.symbol
+            );
+        }
+    }
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for (symbol, price) in source.next_tick() {
+                feed.publish(symbol, price);
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use crate::events::DomainEvent;
+use crate::storage::{StorageResult, Store};
+use crate::{TradeStatus, User};
+
+// ================= Trader Statistics =================
+
+// Fold one realized PnL into the user's running statistics
+pub fn apply_closed_pnl(user: &mut User, pnl: f64) {
+    user.trade_count += 1;
+    user.total_pnl += pnl;
+    if pnl > 0.0 {
+        user.winning_trades += 1;
+        user.gross_profit += pnl;
+    } else if pnl < 0.0 {
+        user.losing_trades += 1;
+        user.gross_loss += pnl;
+    }
+    user.best_trade = Some(user.best_trade.map_or(pnl, |best| best.max(pnl)));
+    user.worst_trade = Some(user.worst_trade.map_or(pnl, |worst| worst.min(pnl)));
+    user.win_rate = user.winning_trades as f64 / user.trade_count as f64;
+}
+
+// Update the user's statistics after one of their positions closed
+pub fn record_close(db: &Store, user_id: &str, pnl: f64) -> StorageResult<()> {
+    let _accounts = db.lock_accounts();
+    let Some(mut user) = db.get_user(user_id)? else {
+        return Ok(());
+    };
+    apply_closed_pnl(&mut user, pnl);
+    db.record(DomainEvent::StatsUpdated { user })
+}
+
+// Rebuild every user's statistics and follower count from history.
+// Only users whose numbers actually changed produce an event.
+pub fn recompute_all(db: &Store) -> StorageResult<()> {
+    let trades = db.list_trades()?;
+    let copied_trades = db.list_copied_trades()?;
+    let relations = db.list_copy_relations()?;
+
+    let _accounts = db.lock_accounts();
+    for user in db.list_users()? {
+        let closed_pnls: Vec<f64> = trades
+            .iter()
+            .filter(|t| t.trader_id == user.id && t.status == TradeStatus::Closed)
+            .filter_map(|t| t.pnl)
+            .chain(
+                copied_trades
+                    .iter()
+                    .filter(|ct| ct.follower_id == user.id && ct.status == TradeStatus::Closed)
+                    .filter_map(|ct| ct.pnl),
+            )
+            .collect();
+
+        let mut updated = User {
+            total_pnl: 0.0,
+            win_rate: 0.0,
+            trade_count: 0,
+            winning_trades: 0,
+            losing_trades: 0,
+            gross_profit: 0.0,
+            gross_loss: 0.0,
+            best_trade: None,
+            worst_trade: None,
+            followers_count: relations
+                .iter()
+                .filter(|r| r.trader_id == user.id && r.status.is_following())
+                .count() as i32,
+            ..user.clone()
+        };
+        for pnl in closed_pnls {
+            apply_closed_pnl(&mut updated, pnl);
+        }
+        if updated != user {
+            db.record(DomainEvent::StatsUpdated { user: updated })?;
+        }
+    }
+    Ok(())
+}
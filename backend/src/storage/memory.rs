@@ -0,0 +1,232 @@
+use super::{Repository, StorageResult};
+use crate::admin::AdminAction;
+use crate::orders::Order;
+use crate::workflows::WorkflowInstance;
+use crate::{BalanceEntry, CopiedTrade, CopyRelation, Trade, User};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+
+// ================= In-Memory Database =================
+
+#[derive(Default)]
+pub struct Database {
+    pub users: HashMap<String, User>,
+    pub trades: HashMap<String, Trade>,
+    pub copy_relations: HashMap<String, CopyRelation>,
+    pub copied_trades: HashMap<String, CopiedTrade>,
+    pub balance_entries: HashMap<String, BalanceEntry>,
+    pub orders: HashMap<String, Order>,
+    pub workflow_instances: HashMap<String, WorkflowInstance>,
+    pub admin_actions: HashMap<String, AdminAction>,
+}
+
+// Volatile repository: everything is lost on restart
+#[derive(Default)]
+pub struct MemoryRepository {
+    db: RwLock<Database>,
+}
+
+impl Repository for MemoryRepository {
+    fn get_user(&self, id: &str) -> StorageResult<Option<User>> {
+        Ok(self.db.read().users.get(id).cloned())
+    }
+
+    fn list_users(&self) -> StorageResult<Vec<User>> {
+        Ok(self.db.read().users.values().cloned().collect())
+    }
+
+    fn save_user(&self, user: &User) -> StorageResult<()> {
+        self.db
+            .write()
+            .users
+            .insert(user.id.to_string(), user.clone());
+        Ok(())
+    }
+
+    fn get_trade(&self, id: &str) -> StorageResult<Option<Trade>> {
+        Ok(self.db.read().trades.get(id).cloned())
+    }
+
+    fn list_trades(&self) -> StorageResult<Vec<Trade>> {
+        Ok(self.db.read().trades.values().cloned().collect())
+    }
+
+    fn trades_by_trader(&self, trader_id: &str) -> StorageResult<Vec<Trade>> {
+        Ok(self
+            .db
+            .read()
+            .trades
+            .values()
+            .filter(|t| t.trader_id.as_str() == trader_id)
+            .cloned()
+            .collect())
+    }
+
+    fn save_trade(&self, trade: &Trade) -> StorageResult<()> {
+        self.db
+            .write()
+            .trades
+            .insert(trade.id.to_string(), trade.clone());
+        Ok(())
+    }
+
+    fn get_copy_relation(&self, id: &str) -> StorageResult<Option<CopyRelation>> {
+        Ok(self.db.read().copy_relations.get(id).cloned())
+    }
+
+    fn list_copy_relations(&self) -> StorageResult<Vec<CopyRelation>> {
+        Ok(self.db.read().copy_relations.values().cloned().collect())
+    }
+
+    fn copy_relations_by_follower(&self, follower_id: &str) -> StorageResult<Vec<CopyRelation>> {
+        Ok(self
+            .db
+            .read()
+            .copy_relations
+            .values()
+            .filter(|r| r.follower_id.as_str() == follower_id)
+            .cloned()
+            .collect())
+    }
+
+    fn copy_relations_by_trader(&self, trader_id: &str) -> StorageResult<Vec<CopyRelation>> {
+        Ok(self
+            .db
+            .read()
+            .copy_relations
+            .values()
+            .filter(|r| r.trader_id.as_str() == trader_id)
+            .cloned()
+            .collect())
+    }
+
+    fn save_copy_relation(&self, relation: &CopyRelation) -> StorageResult<()> {
+        self.db
+            .write()
+            .copy_relations
+            .insert(relation.id.to_string(), relation.clone());
+        Ok(())
+    }
+
+    fn get_copied_trade(&self, id: &str) -> StorageResult<Option<CopiedTrade>> {
+        Ok(self.db.read().copied_trades.get(id).cloned())
+    }
+
+    fn list_copied_trades(&self) -> StorageResult<Vec<CopiedTrade>> {
+        Ok(self.db.read().copied_trades.values().cloned().collect())
+    }
+
+    fn copied_trades_by_follower(&self, follower_id: &str) -> StorageResult<Vec<CopiedTrade>> {
+        Ok(self
+            .db
+            .read()
+            .copied_trades
+            .values()
+            .filter(|t| t.follower_id.as_str() == follower_id)
+            .cloned()
+            .collect())
+    }
+
+    fn copied_trades_by_trade(&self, trade_id: &str) -> StorageResult<Vec<CopiedTrade>> {
+        Ok(self
+            .db
+            .read()
+            .copied_trades
+            .values()
+            .filter(|t| t.original_trade_id.as_str() == trade_id)
+            .cloned()
+            .collect())
+    }
+
+    fn save_copied_trade(&self, copied_trade: &CopiedTrade) -> StorageResult<()> {
+        self.db
+            .write()
+            .copied_trades
+            .insert(copied_trade.id.to_string(), copied_trade.clone());
+        Ok(())
+    }
+
+    fn list_balance_entries(&self) -> StorageResult<Vec<BalanceEntry>> {
+        Ok(self.db.read().balance_entries.values().cloned().collect())
+    }
+
+    fn balance_entries_by_user(&self, user_id: &str) -> StorageResult<Vec<BalanceEntry>> {
+        Ok(self
+            .db
+            .read()
+            .balance_entries
+            .values()
+            .filter(|e| e.user_id.as_str() == user_id)
+            .cloned()
+            .collect())
+    }
+
+    fn save_balance_entry(&self, entry: &BalanceEntry) -> StorageResult<()> {
+        self.db
+            .write()
+            .balance_entries
+            .insert(entry.id.to_string(), entry.clone());
+        Ok(())
+    }
+
+    fn get_order(&self, id: &str) -> StorageResult<Option<Order>> {
+        Ok(self.db.read().orders.get(id).cloned())
+    }
+
+    fn list_orders(&self) -> StorageResult<Vec<Order>> {
+        Ok(self.db.read().orders.values().cloned().collect())
+    }
+
+    fn orders_by_trader(&self, trader_id: &str) -> StorageResult<Vec<Order>> {
+        Ok(self
+            .db
+            .read()
+            .orders
+            .values()
+            .filter(|o| o.trader_id.as_str() == trader_id)
+            .cloned()
+            .collect())
+    }
+
+    fn save_order(&self, order: &Order) -> StorageResult<()> {
+        self.db
+            .write()
+            .orders
+            .insert(order.id.to_string(), order.clone());
+        Ok(())
+    }
+
+    fn get_workflow_instance(&self, id: &str) -> StorageResult<Option<WorkflowInstance>> {
+        Ok(self.db.read().workflow_instances.get(id).cloned())
+    }
+
+    fn list_workflow_instances(&self) -> StorageResult<Vec<WorkflowInstance>> {
+        Ok(self
+            .db
+            .read()
+            .workflow_instances
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn save_workflow_instance(&self, instance: &WorkflowInstance) -> StorageResult<()> {
+        self.db
+            .write()
+            .workflow_instances
+            .insert(instance.id.to_string(), instance.clone());
+        Ok(())
+    }
+
+    fn list_admin_actions(&self) -> StorageResult<Vec<AdminAction>> {
+        Ok(self.db.read().admin_actions.values().cloned().collect())
+    }
+
+    fn save_admin_action(&self, action: &AdminAction) -> StorageResult<()> {
+        self.db
+            .write()
+            .admin_actions
+            .insert(action.id.to_string(), action.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use crate::admin::AdminAction;
+use crate::events::{DomainEvent, EventBus, EventLog};
+use crate::orders::Order;
+use crate::workflows::WorkflowInstance;
+use crate::{BalanceEntry, CopiedTrade, CopyRelation, Trade, User};
+use parking_lot::{Mutex, MutexGuard};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+mod memory;
+mod sqlite;
+
+pub use memory::MemoryRepository;
+pub use sqlite::SqliteRepository;
+
+pub type StorageResult<T> = Result<T, String>;
+
+// ================= Repository =================
+
+// Storage abstraction used by resolvers and BPMN tasks.
+// Implementations must be safe to share across request threads.
+pub trait Repository: Send + Sync {
+    // Users
+    fn get_user(&self, id: &str) -> StorageResult<Option<User>>;
+    fn list_users(&self) -> StorageResult<Vec<User>>;
+    fn save_user(&self, user: &User) -> StorageResult<()>;
+
+    // Trades
+    fn get_trade(&self, id: &str) -> StorageResult<Option<Trade>>;
+    fn list_trades(&self) -> StorageResult<Vec<Trade>>;
+    fn trades_by_trader(&self, trader_id: &str) -> StorageResult<Vec<Trade>>;
+    fn save_trade(&self, trade: &Trade) -> StorageResult<()>;
+
+    // Copy relations
+    fn get_copy_relation(&self, id: &str) -> StorageResult<Option<CopyRelation>>;
+    fn list_copy_relations(&self) -> StorageResult<Vec<CopyRelation>>;
+    fn copy_relations_by_follower(&self, follower_id: &str) -> StorageResult<Vec<CopyRelation>>;
+    fn copy_relations_by_trader(&self, trader_id: &str) -> StorageResult<Vec<CopyRelation>>;
+    fn save_copy_relation(&self, relation: &CopyRelation) -> StorageResult<()>;
+
+    // Copied trades
+    fn get_copied_trade(&self, id: &str) -> StorageResult<Option<CopiedTrade>>;
+    fn list_copied_trades(&self) -> StorageResult<Vec<CopiedTrade>>;
+    fn copied_trades_by_follower(&self, follower_id: &str) -> StorageResult<Vec<CopiedTrade>>;
+    fn copied_trades_by_trade(&self, trade_id: &str) -> StorageResult<Vec<CopiedTrade>>;
+    fn save_copied_trade(&self, copied_trade: &CopiedTrade) -> StorageResult<()>;
+
+    // Balance ledger
+    fn list_balance_entries(&self) -> StorageResult<Vec<BalanceEntry>>;
+    fn balance_entries_by_user(&self, user_id: &str) -> StorageResult<Vec<BalanceEntry>>;
+    fn save_balance_entry(&self, entry: &BalanceEntry) -> StorageResult<()>;
+
+    // Orders
+    fn get_order(&self, id: &str) -> StorageResult<Option<Order>>;
+    fn list_orders(&self) -> StorageResult<Vec<Order>>;
+    fn orders_by_trader(&self, trader_id: &str) -> StorageResult<Vec<Order>>;
+    fn save_order(&self, order: &Order) -> StorageResult<()>;
+
+    // Workflow instances
+    fn get_workflow_instance(&self, id: &str) -> StorageResult<Option<WorkflowInstance>>;
+    fn list_workflow_instances(&self) -> StorageResult<Vec<WorkflowInstance>>;
+    fn save_workflow_instance(&self, instance: &WorkflowInstance) -> StorageResult<()>;
+
+    // Admin audit log
+    fn list_admin_actions(&self) -> StorageResult<Vec<AdminAction>>;
+    fn save_admin_action(&self, action: &AdminAction) -> StorageResult<()>;
+
+    // True when no users exist yet (used to decide whether to seed sample data)
+    fn is_empty(&self) -> StorageResult<bool> {
+        Ok(self.list_users()?.is_empty())
+    }
+}
+
+// ================= Store =================
+
+// Repository plus the optional event log. All writes go through `record` so
+// every state change is logged before it is applied and then published on the
+// event bus; an event that fails to apply is taken back out of the log. Reads
+// deref to the repository.
+pub struct Store {
+    repo: Box<dyn Repository>,
+    log: Option<Mutex<EventLog>>,
+    bus: EventBus,
+    accounts: Mutex<()>,
+    orders: Mutex<()>,
+    positions: Mutex<()>,
+    relations: Mutex<()>,
+}
+
+impl Store {
+    pub fn new(repo: Box<dyn Repository>) -> Self {
+        Store {
+            repo,
+            log: None,
+            bus: EventBus::new(EVENT_BUS_CAPACITY),
+            accounts: Mutex::new(()),
+            orders: Mutex::new(()),
+            positions: Mutex::new(()),
+            relations: Mutex::new(()),
+        }
+    }
+
+    // Rebuild the repository from the event log in `dir`, then keep logging to it
+    pub fn with_event_log(
+        repo: Box<dyn Repository>,
+        dir: &Path,
+        snapshot_interval: u64,
+    ) -> StorageResult<Self> {
+        let log = EventLog::open_and_replay(dir, snapshot_interval, repo.as_ref())?;
+        Ok(Store {
+            repo,
+            log: Some(Mutex::new(log)),
+            bus: EventBus::new(EVENT_BUS_CAPACITY),
+            accounts: Mutex::new(()),
+            orders: Mutex::new(()),
+            positions: Mutex::new(()),
+            relations: Mutex::new(()),
+        })
+    }
+
+    // Record a domain event: append it to the log (if any), apply it, publish it
+    pub fn record(&self, event: DomainEvent) -> StorageResult<()> {
+        let Some(log) = &self.log else {
+            event.apply(self.repo.as_ref())?;
+            self.bus.publish(event);
+            return Ok(());
+        };
+        // Hold the log lock while applying so log order matches apply order
+        let mut log = log.lock();
+        let record = log.append(event)?;
+        if let Err(e) = record.event.apply(self.repo.as_ref()) {
+            log.retract(record.seq)?;
+            return Err(e);
+        }
+        // The event is committed now; a failed snapshot is retried on the next one
+        if let Err(e) = log.maybe_snapshot(self.repo.as_ref()) {
+            println!("⚠️ Snapshot failed: {}", e);
+        }
+        self.bus.publish(record.event);
+        Ok(())
+    }
+
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
+    }
+
+    // Serialize read-modify-write cycles on user balances
+    pub fn lock_accounts(&self) -> MutexGuard<'_, ()> {
+        self.accounts.lock()
+    }
+
+    // Serialize pending order transitions (fill, amend, cancel, expire) so an
+    // order is filled once. A fill holds it while the trade opens, so take it
+    // before every other lock, never after.
+    pub fn lock_orders(&self) -> MutexGuard<'_, ()> {
+        self.orders.lock()
+    }
+
+    // Serialize position state transitions (open -> closed, increased) so a
+    // trade is settled once.
+    // Take this after lock_orders and before lock_accounts.
+    pub fn lock_positions(&self) -> MutexGuard<'_, ()> {
+        self.positions.lock()
+    }
+
+    // Serialize read-modify-write cycles on copy relations (status, settings).
+    // Take this after lock_positions and lock_accounts, never before.
+    pub fn lock_relations(&self) -> MutexGuard<'_, ()> {
+        self.relations.lock()
+    }
+}
+
+impl Deref for Store {
+    type Target = dyn Repository;
+
+    fn deref(&self) -> &Self::Target {
+        self.repo.as_ref()
+    }
+}
+
+pub type DbPool = Arc<Store>;
+
+// Events a slow subscriber may fall behind before it starts missing some
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+// Open the storage backend selected by STORAGE_BACKEND (memory | sqlite).
+// SQLite uses SQLITE_PATH, defaulting to ./copytrade.db.
+// When EVENT_LOG_DIR is set, the repository is rebuilt from the event log there
+// and a snapshot is written every SNAPSHOT_INTERVAL events (default 100).
+pub fn open_from_env() -> StorageResult<DbPool> {
+    let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "memory".to_string());
+    let repo: Box<dyn Repository> = match backend.as_str() {
+        "memory" => {
+            println!("💾 Storage: in-memory");
+            Box::new(MemoryRepository::default())
+        }
+        "sqlite" => {
+            let path = std::env::var("SQLITE_PATH").unwrap_or_else(|_| "copytrade.db".to_string());
+            println!("💾 Storage: SQLite ({})", path);
+            Box::new(SqliteRepository::open(&path)?)
+        }
+        other => return Err(format!("Unknown STORAGE_BACKEND: {}", other)),
+    };
+
+    let store = match std::env::var("EVENT_LOG_DIR") {
+        Ok(dir) => {
+            let interval = match std::env::var("SNAPSHOT_INTERVAL") {
+                Ok(v) => v
+                    .parse()
+                    .map_err(|_| format!("Invalid SNAPSHOT_INTERVAL: {}", v))?,
+                Err(_) => 100,
+            };
+            println!("📜 Event log: {}", dir);
+            Store::with_event_log(repo, Path::new(&dir), interval)?
+        }
+        Err(_) => Store::new(repo),
+    };
+    Ok(Arc::new(store))
+}
@@ -0,0 +1,342 @@
+use super::{Repository, StorageResult};
+use crate::admin::AdminAction;
+use crate::orders::Order;
+use crate::workflows::WorkflowInstance;
+use crate::{BalanceEntry, CopiedTrade, CopyRelation, Trade, User};
+use parking_lot::Mutex;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Serialize, de::DeserializeOwned};
+
+// ================= SQLite Repository =================
+
+// Schema migrations, applied in order. The index into this list + 1 is stored
+// in `PRAGMA user_version`, so only ever append new entries.
+// Rows keep the serialized model in `data`; the other columns exist for lookups.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE users (
+        id TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE trades (
+        id TEXT PRIMARY KEY,
+        trader_id TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_trades_trader ON trades(trader_id);
+    CREATE TABLE copy_relations (
+        id TEXT PRIMARY KEY,
+        follower_id TEXT NOT NULL,
+        trader_id TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_copy_relations_follower ON copy_relations(follower_id);
+    CREATE INDEX idx_copy_relations_trader ON copy_relations(trader_id);
+    CREATE TABLE copied_trades (
+        id TEXT PRIMARY KEY,
+        follower_id TEXT NOT NULL,
+        original_trade_id TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_copied_trades_follower ON copied_trades(follower_id);
+    CREATE INDEX idx_copied_trades_trade ON copied_trades(original_trade_id);",
+    // 2: balance ledger
+    "CREATE TABLE balance_entries (
+        id TEXT PRIMARY KEY,
+        user_id TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_balance_entries_user ON balance_entries(user_id);",
+    // 3: pending orders
+    "CREATE TABLE orders (
+        id TEXT PRIMARY KEY,
+        trader_id TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_orders_trader ON orders(trader_id);",
+    // 4: workflow audit trail
+    "CREATE TABLE workflow_instances (
+        id TEXT PRIMARY KEY,
+        workflow TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_workflow_instances_workflow ON workflow_instances(workflow);",
+    // 5: admin audit log
+    "CREATE TABLE admin_actions (
+        id TEXT PRIMARY KEY,
+        target_id TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_admin_actions_target ON admin_actions(target_id);",
+];
+
+// Embedded file-based repository (no external server)
+pub struct SqliteRepository {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteRepository {
+    pub fn open(path: &str) -> StorageResult<Self> {
+        let conn = Connection::open(path).map_err(|e| format!("SQLite open error: {}", e))?;
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| format!("SQLite pragma error: {}", e))?;
+        let repo = SqliteRepository {
+            conn: Mutex::new(conn),
+        };
+        repo.migrate()?;
+        Ok(repo)
+    }
+
+    // Apply every migration newer than the stored schema version
+    fn migrate(&self) -> StorageResult<()> {
+        let mut conn = self.conn.lock();
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| format!("SQLite migration error: {}", e))?;
+        for (index, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn
+                .transaction()
+                .map_err(|e| format!("SQLite migration error: {}", e))?;
+            tx.execute_batch(sql)
+                .and_then(|_| tx.pragma_update(None, "user_version", index + 1))
+                .and_then(|_| tx.commit())
+                .map_err(|e| format!("SQLite migration {} failed: {}", index + 1, e))?;
+            println!("    ✅ SQLite migration {} applied", index + 1);
+        }
+        Ok(())
+    }
+
+    // Run a SELECT returning the `data` column and decode each row
+    fn query<T: DeserializeOwned>(&self, sql: &str, args: &[&str]) -> StorageResult<Vec<T>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn
+            .prepare_cached(sql)
+            .map_err(|e| format!("SQLite query error: {}", e))?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(args), |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(|e| format!("SQLite query error: {}", e))?;
+        rows.map(|row| {
+            let data = row.map_err(|e| format!("SQLite row error: {}", e))?;
+            serde_json::from_str(&data).map_err(|e| format!("SQLite decode error: {}", e))
+        })
+        .collect()
+    }
+
+    fn query_one<T: DeserializeOwned>(&self, sql: &str, id: &str) -> StorageResult<Option<T>> {
+        let conn = self.conn.lock();
+        let data: Option<String> = conn
+            .query_row(sql, params![id], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("SQLite query error: {}", e))?;
+        data.map(|d| serde_json::from_str(&d).map_err(|e| format!("SQLite decode error: {}", e)))
+            .transpose()
+    }
+
+    fn execute(&self, sql: &str, args: &[&str]) -> StorageResult<()> {
+        self.conn
+            .lock()
+            .execute(sql, rusqlite::params_from_iter(args))
+            .map(|_| ())
+            .map_err(|e| format!("SQLite write error: {}", e))
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> StorageResult<String> {
+    serde_json::to_string(value).map_err(|e| format!("SQLite encode error: {}", e))
+}
+
+impl Repository for SqliteRepository {
+    fn get_user(&self, id: &str) -> StorageResult<Option<User>> {
+        self.query_one("SELECT data FROM users WHERE id = ?1", id)
+    }
+
+    fn list_users(&self) -> StorageResult<Vec<User>> {
+        self.query("SELECT data FROM users ORDER BY rowid", &[])
+    }
+
+    fn save_user(&self, user: &User) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO users (id, data) VALUES (?1, ?2)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            &[user.id.as_str(), &encode(user)?],
+        )
+    }
+
+    fn get_trade(&self, id: &str) -> StorageResult<Option<Trade>> {
+        self.query_one("SELECT data FROM trades WHERE id = ?1", id)
+    }
+
+    fn list_trades(&self) -> StorageResult<Vec<Trade>> {
+        self.query("SELECT data FROM trades ORDER BY rowid", &[])
+    }
+
+    fn trades_by_trader(&self, trader_id: &str) -> StorageResult<Vec<Trade>> {
+        self.query(
+            "SELECT data FROM trades WHERE trader_id = ?1 ORDER BY rowid",
+            &[trader_id],
+        )
+    }
+
+    fn save_trade(&self, trade: &Trade) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO trades (id, trader_id, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            &[trade.id.as_str(), trade.trader_id.as_str(), &encode(trade)?],
+        )
+    }
+
+    fn get_copy_relation(&self, id: &str) -> StorageResult<Option<CopyRelation>> {
+        self.query_one("SELECT data FROM copy_relations WHERE id = ?1", id)
+    }
+
+    fn list_copy_relations(&self) -> StorageResult<Vec<CopyRelation>> {
+        self.query("SELECT data FROM copy_relations ORDER BY rowid", &[])
+    }
+
+    fn copy_relations_by_follower(&self, follower_id: &str) -> StorageResult<Vec<CopyRelation>> {
+        self.query(
+            "SELECT data FROM copy_relations WHERE follower_id = ?1 ORDER BY rowid",
+            &[follower_id],
+        )
+    }
+
+    fn copy_relations_by_trader(&self, trader_id: &str) -> StorageResult<Vec<CopyRelation>> {
+        self.query(
+            "SELECT data FROM copy_relations WHERE trader_id = ?1 ORDER BY rowid",
+            &[trader_id],
+        )
+    }
+
+    fn save_copy_relation(&self, relation: &CopyRelation) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO copy_relations (id, follower_id, trader_id, data) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            &[
+                relation.id.as_str(),
+                relation.follower_id.as_str(),
+                relation.trader_id.as_str(),
+                &encode(relation)?,
+            ],
+        )
+    }
+
+    fn get_copied_trade(&self, id: &str) -> StorageResult<Option<CopiedTrade>> {
+        self.query_one("SELECT data FROM copied_trades WHERE id = ?1", id)
+    }
+
+    fn list_copied_trades(&self) -> StorageResult<Vec<CopiedTrade>> {
+        self.query("SELECT data FROM copied_trades ORDER BY rowid", &[])
+    }
+
+    fn copied_trades_by_follower(&self, follower_id: &str) -> StorageResult<Vec<CopiedTrade>> {
+        self.query(
+            "SELECT data FROM copied_trades WHERE follower_id = ?1 ORDER BY rowid",
+            &[follower_id],
+        )
+    }
+
+    fn copied_trades_by_trade(&self, trade_id: &str) -> StorageResult<Vec<CopiedTrade>> {
+        self.query(
+            "SELECT data FROM copied_trades WHERE original_trade_id = ?1 ORDER BY rowid",
+            &[trade_id],
+        )
+    }
+
+    fn save_copied_trade(&self, copied_trade: &CopiedTrade) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO copied_trades (id, follower_id, original_trade_id, data) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            &[
+                copied_trade.id.as_str(),
+                copied_trade.follower_id.as_str(),
+                copied_trade.original_trade_id.as_str(),
+                &encode(copied_trade)?,
+            ],
+        )
+    }
+
+    fn list_balance_entries(&self) -> StorageResult<Vec<BalanceEntry>> {
+        self.query("SELECT data FROM balance_entries ORDER BY rowid", &[])
+    }
+
+    fn balance_entries_by_user(&self, user_id: &str) -> StorageResult<Vec<BalanceEntry>> {
+        self.query(
+            "SELECT data FROM balance_entries WHERE user_id = ?1 ORDER BY rowid",
+            &[user_id],
+        )
+    }
+
+    fn save_balance_entry(&self, entry: &BalanceEntry) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO balance_entries (id, user_id, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            &[entry.id.as_str(), entry.user_id.as_str(), &encode(entry)?],
+        )
+    }
+
+    fn get_order(&self, id: &str) -> StorageResult<Option<Order>> {
+        self.query_one("SELECT data FROM orders WHERE id = ?1", id)
+    }
+
+    fn list_orders(&self) -> StorageResult<Vec<Order>> {
+        self.query("SELECT data FROM orders ORDER BY rowid", &[])
+    }
+
+    fn orders_by_trader(&self, trader_id: &str) -> StorageResult<Vec<Order>> {
+        self.query(
+            "SELECT data FROM orders WHERE trader_id = ?1 ORDER BY rowid",
+            &[trader_id],
+        )
+    }
+
+    fn save_order(&self, order: &Order) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO orders (id, trader_id, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            &[order.id.as_str(), order.trader_id.as_str(), &encode(order)?],
+        )
+    }
+
+    fn get_workflow_instance(&self, id: &str) -> StorageResult<Option<WorkflowInstance>> {
+        self.query_one("SELECT data FROM workflow_instances WHERE id = ?1", id)
+    }
+
+    fn list_workflow_instances(&self) -> StorageResult<Vec<WorkflowInstance>> {
+        self.query("SELECT data FROM workflow_instances ORDER BY rowid", &[])
+    }
+
+    fn save_workflow_instance(&self, instance: &WorkflowInstance) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO workflow_instances (id, workflow, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            &[instance.id.as_str(), &instance.workflow, &encode(instance)?],
+        )
+    }
+
+    fn list_admin_actions(&self) -> StorageResult<Vec<AdminAction>> {
+        self.query("SELECT data FROM admin_actions ORDER BY rowid", &[])
+    }
+
+    fn save_admin_action(&self, action: &AdminAction) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO admin_actions (id, target_id, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            &[
+                action.id.as_str(),
+                action.target_id.as_str(),
+                &encode(action)?,
+            ],
+        )
+    }
+
+    fn is_empty(&self) -> StorageResult<bool> {
+        let count: i64 = self
+            .conn
+            .lock()
+            .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+            .map_err(|e| format!("SQLite query error: {}", e))?;
+        Ok(count == 0)
+    }
+}
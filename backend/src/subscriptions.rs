@@ -0,0 +1,157 @@
+use crate::auth;
+use crate::events::DomainEvent;
+use crate::orders::Order;
+use crate::prices::{PricePool, PriceQuote};
+use crate::storage::DbPool;
+use crate::{BalanceEntry, CopiedTrade, Trade, User};
+use async_graphql::futures_util::{Stream, StreamExt, stream};
+use async_graphql::{Context, ErrorExtensions, ID, SimpleObject, Subscription};
+use std::future::ready;
+use tokio::sync::broadcast::error::RecvError;
+
+// ================= GraphQL Subscriptions =================
+
+// Payload for userBalanceChanged: the account after the movement plus the movement itself
+#[derive(Clone, SimpleObject)]
+pub struct BalanceChange {
+    pub user: User,
+    pub entry: BalanceEntry,
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    // Trades opened, optionally only for one trader
+    async fn trade_opened(
+        &self,
+        ctx: &Context<'_>,
+        trader_id: Option<ID>,
+    ) -> impl Stream<Item = Trade> + use<> {
+        events(ctx).filter_map(move |event| {
+            ready(match event {
+                DomainEvent::TradeOpened { trade } if matches(&trader_id, &trade.trader_id) => {
+                    Some(trade)
+                }
+                _ => None,
+            })
+        })
+    }
+
+    // Trades closed, optionally only for one trader
+    async fn trade_closed(
+        &self,
+        ctx: &Context<'_>,
+        trader_id: Option<ID>,
+    ) -> impl Stream<Item = Trade> + use<> {
+        events(ctx).filter_map(move |event| {
+            ready(match event {
+                DomainEvent::TradeClosed { trade } if matches(&trader_id, &trade.trader_id) => {
+                    Some(trade)
+                }
+                _ => None,
+            })
+        })
+    }
+
+    // Copied trades opened, skipped, resized, detached or closed for the signed-in follower
+    async fn copied_trade_updated(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<impl Stream<Item = CopiedTrade> + use<>> {
+        let follower_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        Ok(events(ctx).filter_map(move |event| {
+            ready(match event {
+                DomainEvent::CopiedTradeOpened { copied_trade }
+                | DomainEvent::CopiedTradeClosed { copied_trade }
+                | DomainEvent::CopiedTradeReduced { copied_trade }
+                | DomainEvent::CopiedTradeIncreased { copied_trade }
+                | DomainEvent::CopiedTradeSkipped { copied_trade }
+                | DomainEvent::CopiedTradeDetached { copied_trade }
+                    if copied_trade.follower_id == follower_id =>
+                {
+                    Some(copied_trade)
+                }
+                _ => None,
+            })
+        }))
+    }
+
+    // Order lifecycle changes (placed, amended, filled, cancelled, expired) for the
+    // signed-in trader
+    async fn order_updated(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<impl Stream<Item = Order> + use<>> {
+        let trader_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        Ok(events(ctx).filter_map(move |event| {
+            ready(match event {
+                DomainEvent::OrderPlaced { order }
+                | DomainEvent::OrderAmended { order }
+                | DomainEvent::OrderFilled { order }
+                | DomainEvent::OrderCancelled { order }
+                | DomainEvent::OrderExpired { order }
+                    if order.trader_id == trader_id =>
+                {
+                    Some(order)
+                }
+                _ => None,
+            })
+        }))
+    }
+
+    // Every balance movement (margin reserve/release, realized PnL) for the signed-in user
+    async fn user_balance_changed(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<impl Stream<Item = BalanceChange> + use<>> {
+        let user_id = auth::acting_user(ctx).map_err(|e| e.extend())?;
+        Ok(events(ctx).filter_map(move |event| {
+            ready(match event {
+                DomainEvent::BalanceChanged { user, entry } if user.id == user_id => {
+                    Some(BalanceChange { user, entry })
+                }
+                _ => None,
+            })
+        }))
+    }
+
+    // Market price ticks, optionally for one symbol
+    async fn price_updated(
+        &self,
+        ctx: &Context<'_>,
+        symbol: Option<String>,
+    ) -> impl Stream<Item = PriceQuote> + use<> {
+        let receiver = ctx.data_unchecked::<PricePool>().subscribe();
+        stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(quote) => return Some((quote, receiver)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .filter(move |quote| ready(symbol.as_ref().is_none_or(|s| *s == quote.symbol)))
+    }
+}
+
+fn matches(filter: &Option<ID>, id: &ID) -> bool {
+    filter.as_ref().is_none_or(|f| f == id)
+}
+
+// Stream of every event recorded from now on
+fn events(ctx: &Context<'_>) -> impl Stream<Item = DomainEvent> + use<> {
+    let receiver = ctx.data_unchecked::<DbPool>().bus().subscribe();
+    stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(RecvError::Lagged(skipped)) => {
+                    println!("⚠️ Subscriber lagged, skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
@@ -0,0 +1,508 @@
+use crate::errors::{DomainError, DomainResult};
+use crate::events::DomainEvent;
+use crate::prices::PricePool;
+use crate::storage::Store;
+use crate::{
+    CloseReason, CopiedTrade, Trade, TradeDirection, TradeStatus, accounting, copying, stats,
+};
+use async_graphql::Enum;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+// ================= Position Lifecycle =================
+
+// How far, relative to the mark, a price a trader asks for may be
+pub const PRICE_TOLERANCE: f64 = 0.01;
+
+// Price a trader's own close or increase executes at: the mark, or `requested`
+// when it is within PRICE_TOLERANCE of the mark
+pub fn execution_price(
+    prices: &PricePool,
+    symbol: &str,
+    field: &str,
+    requested: Option<f64>,
+) -> DomainResult<f64> {
+    let mark = prices
+        .price(symbol)
+        .ok_or_else(|| DomainError::conflict(format!("No market price for {}", symbol)))?;
+    match requested {
+        None => Ok(mark),
+        Some(price) if (price - mark).abs() <= mark * PRICE_TOLERANCE => Ok(price),
+        Some(_) => Err(DomainError::invalid(
+            field,
+            "Price is too far from the market price",
+        )),
+    }
+}
+
+// Close an open trade at `exit_price`, settle the trader's balance and close
+// every open copy of it.
+pub fn close_trade(
+    db: &Store,
+    trade_id: &str,
+    exit_price: f64,
+    reason: CloseReason,
+) -> DomainResult<Trade> {
+    // Re-read under the positions lock so two closers never settle twice
+    let _positions = db.lock_positions();
+    let trade = open_trade(db, trade_id)?;
+    settle_trade(db, trade, exit_price, reason)
+}
+
+// Close `quantity` of an open trade at `exit_price`, realizing PnL and releasing
+// margin for that portion only. Every open copy shrinks by the same fraction,
+// rounded down to whole lots.
+// Closing the whole remaining quantity is a regular close.
+pub fn close_trade_partial(
+    db: &Store,
+    trade_id: &str,
+    quantity: f64,
+    exit_price: f64,
+) -> DomainResult<Trade> {
+    if quantity <= 0.0 {
+        return Err(DomainError::invalid("quantity", "Invalid quantity"));
+    }
+    if exit_price <= 0.0 {
+        return Err(DomainError::invalid("exitPrice", "Invalid price"));
+    }
+    let _positions = db.lock_positions();
+    let mut trade = open_trade(db, trade_id)?;
+    if quantity > trade.quantity + QUANTITY_EPSILON {
+        return Err(DomainError::invalid(
+            "quantity",
+            "Quantity exceeds open position",
+        ));
+    }
+    if trade.quantity - quantity <= QUANTITY_EPSILON {
+        return settle_trade(db, trade, exit_price, CloseReason::Manual);
+    }
+
+    let fraction = quantity / trade.quantity;
+    let pnl = accounting::realized_pnl(trade.direction, trade.entry_price, exit_price, quantity);
+    let released = trade.margin * fraction;
+    trade.quantity = trim_quantity(trade.quantity - quantity);
+    trade.margin -= released;
+    trade.realized_pnl += pnl;
+    db.record(DomainEvent::TradeReduced {
+        trade: trade.clone(),
+    })?;
+    accounting::settle_position(db, &trade.trader_id, released, pnl, &trade.id)?;
+
+    for mut ct in db.copied_trades_by_trade(trade_id)? {
+        if ct.status != TradeStatus::Open || ct.detached {
+            continue;
+        }
+        // A copy too small to shed a whole lot keeps its size
+        let closed = round_to_lot(ct.quantity * fraction, lot_size(&ct.symbol));
+        if closed <= 0.0 {
+            continue;
+        }
+        let pnl = accounting::realized_pnl(ct.direction, ct.entry_price, exit_price, closed);
+        let released = ct.margin * closed / ct.quantity;
+        ct.quantity = trim_quantity(ct.quantity - closed);
+        ct.margin -= released;
+        ct.realized_pnl += pnl;
+        db.record(DomainEvent::CopiedTradeReduced {
+            copied_trade: ct.clone(),
+        })?;
+        accounting::settle_position(db, &ct.follower_id, released, pnl, &ct.id)?;
+        copying::check_drawdown(db, &ct)?;
+    }
+    Ok(trade)
+}
+
+// Add `quantity` at `price` to an open trade. The entry becomes the
+// volume-weighted average and margin is reserved for the added notional.
+// Open copies scale by the same fraction, rounded down to whole lots, and
+// their percentage stop loss and take profit move to the new entry; copies
+// that would add less than a lot, that the follower cannot fund, or that would
+// break the relation's notional or allocation limit keep their size.
+pub fn increase_trade(
+    db: &Store,
+    trade_id: &str,
+    quantity: f64,
+    price: f64,
+) -> DomainResult<Trade> {
+    if quantity <= 0.0 {
+        return Err(DomainError::invalid("quantity", "Invalid quantity"));
+    }
+    if price <= 0.0 {
+        return Err(DomainError::invalid("price", "Invalid price"));
+    }
+    let _positions = db.lock_positions();
+    let mut trade = open_trade(db, trade_id)?;
+    let fraction = quantity / trade.quantity;
+    let margin = accounting::margin_for(price, quantity);
+    accounting::reserve_margin(db, &trade.trader_id, margin, &trade.id)?;
+    trade.entry_price = weighted_entry(trade.entry_price, trade.quantity, price, quantity);
+    trade.quantity = trim_quantity(trade.quantity + quantity);
+    trade.margin += margin;
+    db.record(DomainEvent::TradeIncreased {
+        trade: trade.clone(),
+    })?;
+
+    for mut ct in db.copied_trades_by_trade(trade_id)? {
+        if ct.status != TradeStatus::Open || ct.detached {
+            continue;
+        }
+        let lot = lot_size(&ct.symbol);
+        let added = round_to_lot(ct.quantity * fraction, lot);
+        if added < lot {
+            println!(
+                "    ⚠️ Copy {} not scaled: size {} is below the lot size {}",
+                ct.id.as_str(),
+                ct.quantity * fraction,
+                lot
+            );
+            continue;
+        }
+        let margin = accounting::margin_for(price, added);
+        if let Some(limit) = copying::scale_limit(db, &ct, margin)? {
+            println!("    ⚠️ Copy {} not scaled: {}", ct.id.as_str(), limit);
+            continue;
+        }
+        if let Err(e) = accounting::reserve_margin(db, &ct.follower_id, margin, &ct.id) {
+            println!("    ⚠️ Copy {} not scaled: {}", ct.id.as_str(), e);
+            continue;
+        }
+        ct.entry_price = weighted_entry(ct.entry_price, ct.quantity, price, added);
+        ct.quantity = trim_quantity(ct.quantity + added);
+        ct.margin += margin;
+        copying::reprice_levels(db, &mut ct)?;
+        db.record(DomainEvent::CopiedTradeIncreased {
+            copied_trade: ct.clone(),
+        })?;
+    }
+    Ok(trade)
+}
+
+// Close a single copied trade on its own (e.g. the follower's stop loss hit)
+pub fn close_copied_trade(
+    db: &Store,
+    copied_trade_id: &str,
+    exit_price: f64,
+    reason: CloseReason,
+) -> DomainResult<CopiedTrade> {
+    let _positions = db.lock_positions();
+    let ct = db
+        .get_copied_trade(copied_trade_id)?
+        .ok_or_else(|| DomainError::not_found("CopiedTrade", copied_trade_id))?;
+    if ct.status != TradeStatus::Open {
+        return Err(DomainError::conflict("Copied trade is not open"));
+    }
+    settle_copied_trade(db, ct, exit_price, reason)
+}
+
+// The trade if it exists and is still open. Call with the positions lock held.
+fn open_trade(db: &Store, trade_id: &str) -> DomainResult<Trade> {
+    let trade = db
+        .get_trade(trade_id)?
+        .ok_or_else(|| DomainError::not_found("Trade", trade_id))?;
+    if trade.status == TradeStatus::Closed {
+        return Err(DomainError::conflict("Trade already closed"));
+    }
+    Ok(trade)
+}
+
+fn settle_trade(
+    db: &Store,
+    mut trade: Trade,
+    exit_price: f64,
+    reason: CloseReason,
+) -> DomainResult<Trade> {
+    let pnl = accounting::realized_pnl(
+        trade.direction,
+        trade.entry_price,
+        exit_price,
+        trade.quantity,
+    );
+    trade.exit_price = Some(exit_price);
+    trade.pnl = Some(trade.realized_pnl + pnl);
+    trade.status = TradeStatus::Closed;
+    trade.close_reason = Some(reason);
+    trade.closed_at = Some(Utc::now());
+    db.record(DomainEvent::TradeClosed {
+        trade: trade.clone(),
+    })?;
+    accounting::settle_position(db, &trade.trader_id, trade.margin, pnl, &trade.id)?;
+    stats::record_close(db, &trade.trader_id, trade.realized_pnl + pnl)?;
+
+    // Close copied trades for followers; detached copies stay open
+    for ct in db.copied_trades_by_trade(&trade.id)? {
+        if ct.status == TradeStatus::Open && !ct.detached {
+            settle_copied_trade(db, ct, exit_price, reason)?;
+        }
+    }
+    Ok(trade)
+}
+
+// Follower PnL comes from the copy's own entry and exit fills. Call with the
+// positions lock held and a copy that is still open.
+pub fn settle_copied_trade(
+    db: &Store,
+    mut ct: CopiedTrade,
+    exit_price: f64,
+    reason: CloseReason,
+) -> DomainResult<CopiedTrade> {
+    let pnl = accounting::realized_pnl(ct.direction, ct.entry_price, exit_price, ct.quantity);
+    ct.exit_price = Some(exit_price);
+    ct.pnl = Some(ct.realized_pnl + pnl);
+    ct.status = TradeStatus::Closed;
+    ct.close_reason = Some(reason);
+    ct.closed_at = Some(Utc::now());
+    accounting::settle_position(db, &ct.follower_id, ct.margin, pnl, &ct.id)?;
+    stats::record_close(db, &ct.follower_id, ct.realized_pnl + pnl)?;
+    db.record(DomainEvent::CopiedTradeClosed {
+        copied_trade: ct.clone(),
+    })?;
+    copying::check_drawdown(db, &ct)?;
+    Ok(ct)
+}
+
+// Average entry after adding `added_qty` at `added_price` to `qty` at `entry`
+fn weighted_entry(entry: f64, qty: f64, added_price: f64, added_qty: f64) -> f64 {
+    (entry * qty + added_price * added_qty) / (qty + added_qty)
+}
+
+// Symbols are BASE/QUOTE pairs of uppercase letters and digits, e.g. BTC/USD
+pub fn valid_symbol(symbol: &str) -> bool {
+    let part = |p: &str| {
+        (2..=10).contains(&p.len())
+            && p.chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    };
+    symbol
+        .split_once('/')
+        .is_some_and(|(base, quote)| part(base) && part(quote))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum AssetClass {
+    Crypto,
+    Forex,
+    Commodity,
+    Equity,
+}
+
+const CRYPTO_ASSETS: &[&str] = &[
+    "BTC", "ETH", "SOL", "XRP", "ADA", "DOGE", "DOT", "AVAX", "LINK", "LTC", "BNB", "MATIC",
+];
+const STABLECOINS: &[&str] = &["USDT", "USDC", "DAI", "BUSD"];
+const FIAT_CURRENCIES: &[&str] = &["USD", "EUR", "GBP", "JPY", "CHF", "AUD", "CAD", "NZD"];
+const COMMODITIES: &[&str] = &["XAU", "XAG", "XPT", "WTI", "BRENT", "NATGAS"];
+
+// Classify a BASE/QUOTE symbol: known coins or stablecoin quotes are crypto,
+// two fiat currencies are forex, metals and energy are commodities, anything
+// else is treated as an equity
+pub fn asset_class(symbol: &str) -> AssetClass {
+    let (base, quote) = symbol.split_once('/').unwrap_or((symbol, ""));
+    if CRYPTO_ASSETS.contains(&base) || STABLECOINS.contains(&quote) {
+        AssetClass::Crypto
+    } else if FIAT_CURRENCIES.contains(&base) && FIAT_CURRENCIES.contains(&quote) {
+        AssetClass::Forex
+    } else if COMMODITIES.contains(&base) {
+        AssetClass::Commodity
+    } else {
+        AssetClass::Equity
+    }
+}
+
+// Smallest tradable increment, by base asset
+const LOT_SIZES: &[(&str, f64)] = &[("BTC", 0.0001), ("ETH", 0.001), ("SOL", 0.01)];
+const DEFAULT_LOT_SIZE: f64 = 0.01;
+
+pub fn lot_size(symbol: &str) -> f64 {
+    let base = symbol.split_once('/').map_or(symbol, |(base, _)| base);
+    LOT_SIZES
+        .iter()
+        .find(|(asset, _)| *asset == base)
+        .map_or(DEFAULT_LOT_SIZE, |(_, lot)| *lot)
+}
+
+// Round down to a whole number of lots. The epsilon keeps exact multiples
+// (0.3 / 0.1 = 2.9999...) from losing a lot.
+pub fn round_to_lot(quantity: f64, lot: f64) -> f64 {
+    let lots = (quantity / lot + 1e-9).floor().max(0.0);
+    trim_quantity(lots * lot)
+}
+
+// Sizes closer than this are the same position; subtraction leaves residue
+// such as 1.0 - 0.9 = 0.09999999999999998
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+// Trim float noise such as 0.30000000000000004
+fn trim_quantity(quantity: f64) -> f64 {
+    (quantity * 1e10).round() / 1e10
+}
+
+// ================= Stop Loss / Take Profit =================
+
+// Check that protective levels sit on the correct side of the entry price
+pub fn validate_levels(
+    direction: TradeDirection,
+    entry_price: f64,
+    stop_loss: Option<f64>,
+    take_profit: Option<f64>,
+) -> DomainResult<()> {
+    let (sl_ok, tp_ok) = match direction {
+        TradeDirection::Long => (
+            stop_loss.is_none_or(|sl| sl > 0.0 && sl < entry_price),
+            take_profit.is_none_or(|tp| tp > entry_price),
+        ),
+        TradeDirection::Short => (
+            stop_loss.is_none_or(|sl| sl > entry_price),
+            take_profit.is_none_or(|tp| tp > 0.0 && tp < entry_price),
+        ),
+    };
+    if !sl_ok {
+        Err(DomainError::invalid("stopLoss", "Invalid stop loss"))
+    } else if !tp_ok {
+        Err(DomainError::invalid("takeProfit", "Invalid take profit"))
+    } else {
+        Ok(())
+    }
+}
+
+// Level `pct` percent away from `entry_price`, on the losing (stop) or winning side
+pub fn level_from_pct(direction: TradeDirection, entry_price: f64, pct: f64, stop: bool) -> f64 {
+    let sign = match (direction, stop) {
+        (TradeDirection::Long, true) | (TradeDirection::Short, false) => -1.0,
+        (TradeDirection::Long, false) | (TradeDirection::Short, true) => 1.0,
+    };
+    entry_price * (1.0 + sign * pct / 100.0)
+}
+
+// Which level, if any, `price` has reached
+pub fn triggered(
+    direction: TradeDirection,
+    stop_loss: Option<f64>,
+    take_profit: Option<f64>,
+    price: f64,
+) -> Option<CloseReason> {
+    let (stop_hit, target_hit) = match direction {
+        TradeDirection::Long => (
+            stop_loss.is_some_and(|sl| price <= sl),
+            take_profit.is_some_and(|tp| price >= tp),
+        ),
+        TradeDirection::Short => (
+            stop_loss.is_some_and(|sl| price >= sl),
+            take_profit.is_some_and(|tp| price <= tp),
+        ),
+    };
+    if stop_hit {
+        Some(CloseReason::StopLoss)
+    } else if target_hit {
+        Some(CloseReason::TakeProfit)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::User;
+    use crate::storage::MemoryRepository;
+    use serde_json::json;
+
+    // Trader "t" long `quantity` SOL/USD at 10 and follower "f" with a copy of
+    // `copy_quantity` at the same price, margins reserved for both
+    fn open_position(quantity: f64, copy_quantity: f64) -> Store {
+        let db = Store::new(Box::new(MemoryRepository::default()));
+        for (id, margin) in [("t", 10.0 * quantity), ("f", 10.0 * copy_quantity)] {
+            let mut user = User::new(id, id, 10_000.0 - margin, id == "t");
+            user.margin_used = margin;
+            db.save_user(&user).unwrap();
+        }
+        let trade: Trade = serde_json::from_value(json!({
+            "id": "trade", "trader_id": "t", "symbol": "SOL/USD", "direction": "Long",
+            "entry_price": 10.0, "exit_price": null, "quantity": quantity,
+            "margin": 10.0 * quantity, "pnl": null, "status": "Open",
+            "created_at": Utc::now(), "closed_at": null,
+        }))
+        .unwrap();
+        db.save_trade(&trade).unwrap();
+        let copy: CopiedTrade = serde_json::from_value(json!({
+            "id": "copy", "original_trade_id": "trade", "follower_id": "f",
+            "symbol": "SOL/USD", "direction": "Long", "entry_price": 10.0,
+            "quantity": copy_quantity, "margin": 10.0 * copy_quantity, "pnl": null,
+            "status": "Open",
+        }))
+        .unwrap();
+        db.save_copied_trade(&copy).unwrap();
+        db
+    }
+
+    fn copy(db: &Store) -> CopiedTrade {
+        db.get_copied_trade("copy").unwrap().unwrap()
+    }
+
+    #[test]
+    fn round_to_lot_keeps_exact_multiples() {
+        assert_eq!(round_to_lot(0.3, 0.1), 0.3);
+        assert_eq!(round_to_lot(0.7, 0.1), 0.7);
+        assert_eq!(round_to_lot(0.0003, 0.0001), 0.0003);
+    }
+
+    #[test]
+    fn round_to_lot_rounds_down() {
+        assert_eq!(round_to_lot(0.35, 0.1), 0.3);
+        assert_eq!(round_to_lot(0.0199, 0.01), 0.01);
+        assert_eq!(round_to_lot(0.009, 0.01), 0.0);
+        assert_eq!(round_to_lot(-1.0, 0.01), 0.0);
+    }
+
+    #[test]
+    fn lot_size_by_base_asset() {
+        assert_eq!(lot_size("BTC/USD"), 0.0001);
+        assert_eq!(lot_size("SOL/USDT"), 0.01);
+        assert_eq!(lot_size("XAU/USD"), DEFAULT_LOT_SIZE);
+    }
+
+    #[test]
+    fn partial_close_leaves_trimmed_remainder() {
+        let db = open_position(1.0, 1.0);
+        let trade = close_trade_partial(&db, "trade", 0.9, 12.0).unwrap();
+        assert_eq!(trade.status, TradeStatus::Open);
+        // 1.0 - 0.9 without trimming is 0.09999999999999998
+        assert_eq!(trade.quantity, 0.1);
+        assert!((trade.realized_pnl - 1.8).abs() < 1e-9);
+        assert!((trade.margin - 1.0).abs() < 1e-9);
+        assert_eq!(copy(&db).quantity, 0.1);
+    }
+
+    #[test]
+    fn closing_the_remainder_within_epsilon_closes_the_trade() {
+        let db = open_position(0.3, 0.3);
+        close_trade_partial(&db, "trade", 0.1, 10.0).unwrap();
+        close_trade_partial(&db, "trade", 0.1, 10.0).unwrap();
+        // What is left is 0.1 up to float residue
+        let trade = close_trade_partial(&db, "trade", 0.1 + 1e-12, 11.0).unwrap();
+        assert_eq!(trade.status, TradeStatus::Closed);
+        assert_eq!(trade.close_reason, Some(CloseReason::Manual));
+        assert_eq!(copy(&db).status, TradeStatus::Closed);
+        let trader = db.get_user("t").unwrap().unwrap();
+        assert!(trader.margin_used.abs() < 1e-9);
+    }
+
+    #[test]
+    fn closing_more_than_open_is_rejected() {
+        let db = open_position(1.0, 1.0);
+        let err = close_trade_partial(&db, "trade", 1.001, 10.0).unwrap_err();
+        assert!(matches!(err, DomainError::ValidationFailed { field, .. } if field == "quantity"));
+        assert_eq!(db.get_trade("trade").unwrap().unwrap().quantity, 1.0);
+    }
+
+    #[test]
+    fn copies_shed_whole_lots_only() {
+        // A third of 0.05 is 0.0166..., one SOL lot
+        let db = open_position(3.0, 0.05);
+        close_trade_partial(&db, "trade", 1.0, 10.0).unwrap();
+        assert_eq!(copy(&db).quantity, 0.04);
+
+        // A tenth of 0.04 is below a lot, so the copy keeps its size
+        let db = open_position(10.0, 0.04);
+        close_trade_partial(&db, "trade", 1.0, 10.0).unwrap();
+        assert_eq!(copy(&db).quantity, 0.04);
+    }
+}
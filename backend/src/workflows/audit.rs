@@ -0,0 +1,174 @@
+use crate::errors::DomainError;
+use async_graphql::{Enum, ID, InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use snurr::{Data, Process, TaskResult};
+use std::time::Instant;
+
+// ================= Workflow Instances =================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum WorkflowOutcome {
+    // Reached the Success end event
+    Success,
+    // Reached any other end event, or the engine stopped the run
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum StepKind {
+    Task,
+    Gateway,
+}
+
+// One task or gateway visited during a run
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+pub struct WorkflowStep {
+    pub name: String,
+    pub kind: StepKind,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: f64,
+    // Flow taken out of a gateway, or "Error" for a task that left through
+    // its error boundary event
+    pub branch: Option<String>,
+}
+
+// Record of one run of a BPMN process
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+pub struct WorkflowInstance {
+    pub id: ID,
+    pub workflow: String,
+    // Version numbers restart at 1 on every boot; the checksum names the
+    // diagram that ran for good
+    pub definition_version: u32,
+    #[serde(default)]
+    pub definition_checksum: Option<String>,
+    pub input: serde_json::Value,
+    pub outcome: WorkflowOutcome,
+    // End event the run finished on
+    #[serde(default)]
+    pub end_event: Option<String>,
+    pub error: Option<String>,
+    // Stable code of the error, as in GraphQL error extensions
+    #[serde(default)]
+    pub error_code: Option<String>,
+    // Entity the run created or acted on (trade, copy relation, user)
+    pub subject_id: Option<ID>,
+    pub steps: Vec<WorkflowStep>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: f64,
+}
+
+impl WorkflowInstance {
+    pub fn fail(&mut self, error: &DomainError) {
+        self.outcome = WorkflowOutcome::Failed;
+        self.report(error);
+    }
+
+    // Record the error without deciding the outcome
+    pub fn report(&mut self, error: &DomainError) {
+        self.error = Some(error.to_string());
+        self.error_code = Some(error.code().to_string());
+    }
+}
+
+#[derive(Default, InputObject)]
+pub struct WorkflowInstanceFilter {
+    pub workflow: Option<String>,
+    pub outcome: Option<WorkflowOutcome>,
+    pub subject_id: Option<ID>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl WorkflowInstanceFilter {
+    pub fn matches(&self, instance: &WorkflowInstance) -> bool {
+        self.workflow
+            .as_ref()
+            .is_none_or(|w| *w == instance.workflow)
+            && self.outcome.is_none_or(|o| o == instance.outcome)
+            && self
+                .subject_id
+                .as_ref()
+                .is_none_or(|s| instance.subject_id.as_ref() == Some(s))
+            && self.since.is_none_or(|since| instance.started_at >= since)
+    }
+}
+
+// ================= Step Tracing =================
+
+// Workflow context that collects the steps of its run and reports the result
+pub trait Traceable {
+    fn steps(&mut self) -> &mut Vec<WorkflowStep>;
+
+    // Entity the run produced or acted on, if any
+    fn subject(&self) -> Option<&str>;
+
+    fn error(&mut self) -> &mut Option<DomainError>;
+}
+
+// Process under construction whose handlers record a step each time they run.
+// Mirrors snurr's task/exclusive registration.
+pub struct Traced<T>(Process<T>);
+
+impl<T: Traceable + 'static> Traced<T> {
+    pub fn new(process: Process<T>) -> Self {
+        Traced(process)
+    }
+
+    pub fn into_inner(self) -> Process<T> {
+        self.0
+    }
+
+    pub fn task<F>(self, name: &'static str, func: F) -> Self
+    where
+        F: Fn(Data<T>) -> TaskResult + 'static + Sync + Send,
+    {
+        Traced(self.0.task(name, move |data: Data<T>| {
+            let (started_at, start) = (Utc::now(), Instant::now());
+            let result = func(data.clone());
+            let boundary = result.as_ref().map(ToString::to_string);
+            record(
+                &data,
+                name,
+                StepKind::Task,
+                started_at,
+                start,
+                boundary.as_deref(),
+            );
+            result
+        }))
+    }
+
+    pub fn exclusive<F>(self, name: &'static str, func: F) -> Self
+    where
+        F: Fn(Data<T>) -> Option<&'static str> + 'static + Sync + Send,
+    {
+        Traced(self.0.exclusive(name, move |data: Data<T>| {
+            let (started_at, start) = (Utc::now(), Instant::now());
+            let branch = func(data.clone());
+            record(&data, name, StepKind::Gateway, started_at, start, branch);
+            branch
+        }))
+    }
+}
+
+fn record<T: Traceable>(
+    data: &Data<T>,
+    name: &str,
+    kind: StepKind,
+    started_at: DateTime<Utc>,
+    start: Instant,
+    branch: Option<&str>,
+) {
+    let step = WorkflowStep {
+        name: name.to_string(),
+        kind,
+        started_at,
+        duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+        branch: branch.map(str::to_string),
+    };
+    if let Ok(mut guard) = data.lock() {
+        guard.steps().push(step);
+    }
+}
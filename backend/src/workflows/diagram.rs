@@ -0,0 +1,263 @@
+use super::audit::WorkflowStep;
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+
+// ================= End Events =================
+
+// End event a run reaches after its last task or gateway. snurr does not report
+// which end event a run finished on, so it is read off the diagram: from the
+// flow the last step left through, past unnamed gateways and events, to an end
+// event. Keyed by step name and branch; a task that left through its error
+// boundary has the branch "Error".
+#[derive(Debug, Default)]
+pub struct EndEvents(HashMap<(String, Option<String>), String>);
+
+#[derive(Debug, PartialEq)]
+enum NodeKind {
+    // A handler runs for these and records the step
+    Task,
+    Gateway,
+    // Unnamed gateway or intermediate event: the run passes straight through
+    PassThrough,
+    End,
+    // Boundary event on the task with this id
+    Boundary { task: String, error: bool },
+}
+
+struct Node {
+    kind: NodeKind,
+    name: Option<String>,
+}
+
+struct Flow {
+    id: String,
+    name: Option<String>,
+    source: String,
+    target: String,
+}
+
+const TASKS: &[&[u8]] = &[
+    b"task",
+    b"serviceTask",
+    b"userTask",
+    b"scriptTask",
+    b"manualTask",
+    b"sendTask",
+    b"receiveTask",
+    b"businessRuleTask",
+    b"callActivity",
+];
+const GATEWAYS: &[&[u8]] = &[
+    b"exclusiveGateway",
+    b"inclusiveGateway",
+    b"parallelGateway",
+    b"eventBasedGateway",
+];
+const EVENTS: &[&[u8]] = &[b"intermediateThrowEvent", b"intermediateCatchEvent"];
+
+impl EndEvents {
+    pub fn parse(xml: &str) -> Result<Self, String> {
+        let mut nodes: HashMap<String, Node> = HashMap::new();
+        let mut flows: Vec<Flow> = Vec::new();
+        // Boundary event whose definitions are being read
+        let mut boundary: Option<String> = None;
+        let mut reader = Reader::from_str(xml);
+        loop {
+            let event = reader.read_event().map_err(|e| e.to_string())?;
+            let (element, open) = match &event {
+                Event::Start(e) => (e, true),
+                Event::Empty(e) => (e, false),
+                Event::End(e) => {
+                    if e.local_name().as_ref() == b"boundaryEvent" {
+                        boundary = None;
+                    }
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+            let tag = element.local_name();
+            let tag = tag.as_ref();
+            let id = attribute(element, "id")?;
+            let name = attribute(element, "name")?;
+            let kind = if TASKS.contains(&tag) {
+                NodeKind::Task
+            } else if GATEWAYS.contains(&tag) {
+                if name.is_some() {
+                    NodeKind::Gateway
+                } else {
+                    NodeKind::PassThrough
+                }
+            } else if EVENTS.contains(&tag) {
+                NodeKind::PassThrough
+            } else if tag == b"endEvent" {
+                NodeKind::End
+            } else if tag == b"boundaryEvent" {
+                let task = attribute(element, "attachedToRef")?
+                    .ok_or("Boundary event without attachedToRef")?;
+                if open {
+                    boundary = id.clone();
+                }
+                NodeKind::Boundary { task, error: false }
+            } else if tag == b"errorEventDefinition" {
+                if let Some(Node {
+                    kind: NodeKind::Boundary { error, .. },
+                    ..
+                }) = boundary.as_ref().and_then(|b| nodes.get_mut(b))
+                {
+                    *error = true;
+                }
+                continue;
+            } else if tag == b"sequenceFlow" {
+                let (Some(id), Some(source), Some(target)) = (
+                    id,
+                    attribute(element, "sourceRef")?,
+                    attribute(element, "targetRef")?,
+                ) else {
+                    return Err("Sequence flow without id, sourceRef or targetRef".to_string());
+                };
+                flows.push(Flow {
+                    id,
+                    name,
+                    source,
+                    target,
+                });
+                continue;
+            } else {
+                continue;
+            };
+            if let Some(id) = id {
+                nodes.insert(id, Node { kind, name });
+            }
+        }
+
+        let mut outgoing: HashMap<&str, Vec<&Flow>> = HashMap::new();
+        for flow in &flows {
+            outgoing.entry(flow.source.as_str()).or_default().push(flow);
+        }
+        let outgoing = |id: &str| outgoing.get(id).map_or(&[][..], Vec::as_slice);
+        // Follow a flow to the end event it leads to without another handler
+        let end_of = |flow: &Flow| {
+            let mut target = flow.target.as_str();
+            for _ in 0..=nodes.len() {
+                let node = nodes.get(target)?;
+                match node.kind {
+                    NodeKind::End => return Some(node.name.clone().unwrap_or(target.into())),
+                    NodeKind::PassThrough => match outgoing(target) {
+                        [only] => target = only.target.as_str(),
+                        _ => return None,
+                    },
+                    _ => return None,
+                }
+            }
+            None
+        };
+
+        let mut ends = HashMap::new();
+        for (id, node) in &nodes {
+            match &node.kind {
+                NodeKind::Task | NodeKind::Gateway => {
+                    let Some(name) = &node.name else { continue };
+                    for flow in outgoing(id) {
+                        let Some(end) = end_of(flow) else { continue };
+                        // A gateway's step records the flow it picked by name
+                        let branch = (node.kind == NodeKind::Gateway)
+                            .then(|| flow.name.clone().unwrap_or(flow.id.clone()));
+                        ends.insert((name.clone(), branch), end);
+                    }
+                }
+                NodeKind::Boundary { task, error: true } => {
+                    let Some(name) = nodes.get(task).and_then(|t| t.name.clone()) else {
+                        continue;
+                    };
+                    if let Some(end) = outgoing(id).first().and_then(|flow| end_of(flow)) {
+                        ends.insert((name, Some("Error".to_string())), end);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(EndEvents(ends))
+    }
+
+    // End event reached when `step` was the last one the run took
+    pub fn reached(&self, step: &WorkflowStep) -> Option<&str> {
+        self.0
+            .get(&(step.name.clone(), step.branch.clone()))
+            .map(String::as_str)
+    }
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, String> {
+    element
+        .try_get_attribute(name)
+        .map_err(|e| e.to_string())?
+        .map(|a| a.unescape_value().map(|v| v.into_owned()))
+        .transpose()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflows::audit::StepKind;
+    use chrono::Utc;
+
+    const REGISTER_USER: &str = include_str!("../../bpmn/register_user.bpmn");
+    const STOP_COPYING: &str = include_str!("../../bpmn/stop_copying.bpmn");
+
+    fn step(name: &str, kind: StepKind, branch: Option<&str>) -> WorkflowStep {
+        WorkflowStep {
+            name: name.to_string(),
+            kind,
+            started_at: Utc::now(),
+            duration_ms: 0.0,
+            branch: branch.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn last_task_leads_to_its_end_event() {
+        let ends = EndEvents::parse(REGISTER_USER).unwrap();
+        let created = step("Create User Account", StepKind::Task, None);
+        assert_eq!(ends.reached(&created), Some("Success"));
+        let validated = step("Validate Registration", StepKind::Task, None);
+        assert_eq!(ends.reached(&validated), None);
+    }
+
+    #[test]
+    fn gateway_branch_picks_the_end_event() {
+        let ends = EndEvents::parse(REGISTER_USER).unwrap();
+        let rejected = step("Is Valid", StepKind::Gateway, Some("No"));
+        assert_eq!(ends.reached(&rejected), Some("Failed"));
+        let accepted = step("Is Valid", StepKind::Gateway, Some("Yes"));
+        assert_eq!(ends.reached(&accepted), None);
+    }
+
+    #[test]
+    fn error_boundary_leads_to_its_end_event() {
+        let ends = EndEvents::parse(REGISTER_USER).unwrap();
+        let failed = step("Create User Account", StepKind::Task, Some("Error"));
+        assert_eq!(ends.reached(&failed), Some("Failed"));
+    }
+
+    #[test]
+    fn unnamed_gateways_are_passed_through() {
+        let ends = EndEvents::parse(STOP_COPYING).unwrap();
+        let kept = step("Open Copies", StepKind::Gateway, Some("Keep"));
+        assert_eq!(ends.reached(&kept), Some("Success"));
+        let closed = step("Close Open Copies", StepKind::Task, None);
+        assert_eq!(ends.reached(&closed), Some("Success"));
+        let detached = step("Detach Open Copies", StepKind::Task, None);
+        assert_eq!(ends.reached(&detached), Some("Success"));
+        let picked = step("Open Copies", StepKind::Gateway, Some("Close"));
+        assert_eq!(ends.reached(&picked), None);
+    }
+
+    #[test]
+    fn malformed_flow_is_an_error() {
+        let xml = r#"<definitions><process id="p"><sequenceFlow id="f" sourceRef="a" /></process></definitions>"#;
+        assert!(EndEvents::parse(xml).is_err());
+    }
+}
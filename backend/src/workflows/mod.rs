@@ -0,0 +1,1207 @@
+use crate::auth;
+use crate::copying::{self, CopyFilters, CopyLimits, CopyStatus, SizingMode, StopCopyMode};
+use crate::errors::{DomainError, DomainResult};
+use crate::events::DomainEvent;
+use crate::prices::PricePool;
+use crate::storage::{DbPool, Store};
+use crate::{
+    CloseReason, CopyRelation, CopyTraderInput, CreateTradeInput, Trade, TradeDirection,
+    TradeStatus, User, accounting, trading,
+};
+use async_graphql::ID;
+use audit::{Traceable, Traced, WorkflowOutcome, WorkflowStep};
+use chrono::Utc;
+use registry::{DefinitionSlot, Versioned};
+use snurr::{Boundary, Symbol, TaskResult};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+
+mod audit;
+mod diagram;
+mod registry;
+
+pub use audit::{WorkflowInstance, WorkflowInstanceFilter};
+pub use registry::{WorkflowDefinition, spawn_watcher};
+
+// ================= BPMN Workflow Contexts =================
+
+#[derive(Default)]
+pub struct TradeWorkflowCtx {
+    pub trader_id: String,
+    pub symbol: String,
+    pub direction: String,
+    pub entry_price: f64,
+    pub quantity: f64,
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+    pub trade_id: String,
+    pub instance_id: String,
+    pub is_valid: bool,
+    pub error: Option<DomainError>,
+    pub db: Option<DbPool>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Default)]
+pub struct CopyWorkflowCtx {
+    pub follower_id: String,
+    pub trader_id: String,
+    pub copy_ratio: f64,
+    pub sizing_mode: SizingMode,
+    pub notional: Option<f64>,
+    pub allocated_capital: Option<f64>,
+    pub stop_loss_pct: Option<f64>,
+    pub take_profit_pct: Option<f64>,
+    pub limits: CopyLimits,
+    pub filters: CopyFilters,
+    pub copy_open_positions: bool,
+    pub relation_id: String,
+    pub instance_id: String,
+    pub is_valid: bool,
+    pub error: Option<DomainError>,
+    pub db: Option<DbPool>,
+    // Mark prices for copying open positions
+    pub prices: Option<PricePool>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Default)]
+pub struct CloseTradeWorkflowCtx {
+    pub trade_id: String,
+    // Signed-in user; must be the trade's trader
+    pub actor_id: String,
+    pub exit_price: f64,
+    pub is_valid: bool,
+    pub error: Option<DomainError>,
+    pub db: Option<DbPool>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Default)]
+pub struct StopCopyingWorkflowCtx {
+    pub relation_id: String,
+    // Signed-in user; must be the relation's follower
+    pub actor_id: String,
+    pub trader_id: String,
+    pub mode: StopCopyMode,
+    pub is_valid: bool,
+    pub error: Option<DomainError>,
+    pub db: Option<DbPool>,
+    // Mark prices for closing open copies
+    pub prices: Option<PricePool>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Default)]
+pub struct RegisterUserWorkflowCtx {
+    pub username: String,
+    // Plain text only until "Create User Account" hashes it
+    pub password: String,
+    pub is_trader: bool,
+    pub user_id: String,
+    pub is_valid: bool,
+    pub error: Option<DomainError>,
+    pub db: Option<DbPool>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+impl Traceable for TradeWorkflowCtx {
+    fn steps(&mut self) -> &mut Vec<WorkflowStep> {
+        &mut self.steps
+    }
+    fn subject(&self) -> Option<&str> {
+        Some(self.trade_id.as_str()).filter(|id| !id.is_empty())
+    }
+    fn error(&mut self) -> &mut Option<DomainError> {
+        &mut self.error
+    }
+}
+
+impl Traceable for CopyWorkflowCtx {
+    fn steps(&mut self) -> &mut Vec<WorkflowStep> {
+        &mut self.steps
+    }
+    fn subject(&self) -> Option<&str> {
+        Some(self.relation_id.as_str()).filter(|id| !id.is_empty())
+    }
+    fn error(&mut self) -> &mut Option<DomainError> {
+        &mut self.error
+    }
+}
+
+impl Traceable for CloseTradeWorkflowCtx {
+    fn steps(&mut self) -> &mut Vec<WorkflowStep> {
+        &mut self.steps
+    }
+    fn subject(&self) -> Option<&str> {
+        Some(self.trade_id.as_str())
+    }
+    fn error(&mut self) -> &mut Option<DomainError> {
+        &mut self.error
+    }
+}
+
+impl Traceable for StopCopyingWorkflowCtx {
+    fn steps(&mut self) -> &mut Vec<WorkflowStep> {
+        &mut self.steps
+    }
+    fn subject(&self) -> Option<&str> {
+        Some(self.relation_id.as_str())
+    }
+    fn error(&mut self) -> &mut Option<DomainError> {
+        &mut self.error
+    }
+}
+
+impl Traceable for RegisterUserWorkflowCtx {
+    fn steps(&mut self) -> &mut Vec<WorkflowStep> {
+        &mut self.steps
+    }
+    fn subject(&self) -> Option<&str> {
+        Some(self.user_id.as_str()).filter(|id| !id.is_empty())
+    }
+    fn error(&mut self) -> &mut Option<DomainError> {
+        &mut self.error
+    }
+}
+
+// ================= Workflow Registry =================
+
+// Every BPMN process, built at startup and shared by all requests. Each keeps
+// its version history; see registry.rs.
+pub struct Workflows {
+    create_trade: Versioned<TradeWorkflowCtx>,
+    copy_trader: Versioned<CopyWorkflowCtx>,
+    close_trade: Versioned<CloseTradeWorkflowCtx>,
+    stop_copying: Versioned<StopCopyingWorkflowCtx>,
+    register_user: Versioned<RegisterUserWorkflowCtx>,
+}
+
+pub type WorkflowPool = Arc<Workflows>;
+
+impl Workflows {
+    // Load `<name>.bpmn` for every workflow from `dir`. Fails when a diagram is
+    // missing or invalid, or names a task or gateway without a handler here.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let workflows = Workflows {
+            create_trade: Versioned::load(dir, "create_trade", create_trade_handlers)?,
+            copy_trader: Versioned::load(dir, "copy_trader", copy_trader_handlers)?,
+            close_trade: Versioned::load(dir, "close_trade", close_trade_handlers)?,
+            stop_copying: Versioned::load(dir, "stop_copying", stop_copying_handlers)?,
+            register_user: Versioned::load(dir, "register_user", register_user_handlers)?,
+        };
+        println!("📋 Workflows loaded from {}", dir.display());
+        Ok(workflows)
+    }
+
+    fn slots(&self) -> [&dyn DefinitionSlot; 5] {
+        [
+            &self.create_trade,
+            &self.copy_trader,
+            &self.close_trade,
+            &self.stop_copying,
+            &self.register_user,
+        ]
+    }
+
+    fn slot(&self, name: &str) -> DomainResult<&dyn DefinitionSlot> {
+        self.slots()
+            .into_iter()
+            .find(|slot| slot.name() == name)
+            .ok_or_else(|| DomainError::not_found("Workflow", name))
+    }
+
+    // Rebuild one workflow from its diagram on disk
+    pub fn reload(&self, name: &str) -> DomainResult<Option<u32>> {
+        Ok(self.slot(name)?.reload()?)
+    }
+
+    // Switch a workflow back to an earlier version
+    pub fn rollback(&self, name: &str, version: u32) -> DomainResult<WorkflowDefinition> {
+        self.slot(name)?.rollback(version)
+    }
+
+    // Version history of every workflow, or of one
+    pub fn definitions(&self, name: Option<&str>) -> Vec<WorkflowDefinition> {
+        self.slots()
+            .into_iter()
+            .filter(|slot| name.is_none_or(|n| n == slot.name()))
+            .flat_map(|slot| slot.definitions())
+            .collect()
+    }
+}
+
+// BPMN directory from BPMN_DIR, defaulting to bpmn/ in the working directory
+pub fn dir_from_env() -> PathBuf {
+    std::env::var("BPMN_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("bpmn"))
+}
+
+// ================= BPMN Workflow Execution =================
+
+// Direction is carried as its Debug name in workflow contexts
+fn parse_direction(direction: &str) -> TradeDirection {
+    if direction == "Long" {
+        TradeDirection::Long
+    } else {
+        TradeDirection::Short
+    }
+}
+
+impl Workflows {
+    // Run the active version of a workflow and record the run as a WorkflowInstance
+    fn execute<T: Traceable + Send + 'static>(
+        &self,
+        db: &DbPool,
+        workflow: &Versioned<T>,
+        instance_id: String,
+        input: serde_json::Value,
+        ctx: T,
+    ) -> DomainResult<T> {
+        let active = workflow.active();
+        let (started_at, start) = (Utc::now(), Instant::now());
+        let mut run = active
+            .process
+            .run(ctx)
+            .map_err(|e| DomainError::WorkflowFailed {
+                process: workflow.name().to_string(),
+                node: failed_node(&e),
+                message: format!("Workflow error: {}", e),
+            });
+        let mut instance = WorkflowInstance {
+            id: ID(instance_id),
+            workflow: workflow.name().to_string(),
+            definition_version: active.version,
+            definition_checksum: Some(active.checksum),
+            input,
+            outcome: WorkflowOutcome::Failed,
+            end_event: None,
+            error: None,
+            error_code: None,
+            subject_id: None,
+            steps: Vec::new(),
+            started_at,
+            finished_at: Utc::now(),
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+        };
+        match &mut run {
+            Ok(result) => {
+                instance.subject_id = result.subject().map(|id| ID(id.to_string()));
+                instance.steps = std::mem::take(result.steps());
+                // The end event decides the outcome; an error is only its message
+                instance.end_event = instance
+                    .steps
+                    .last()
+                    .and_then(|step| active.ends.reached(step))
+                    .map(str::to_string);
+                if instance.end_event.as_deref() == Some(SUCCESS_END) {
+                    instance.outcome = WorkflowOutcome::Success;
+                }
+                // A task that hit an unexpected failure fails the workflow at that node
+                let error = result.error();
+                if let Some(DomainError::Internal { message }) = error {
+                    *error = Some(DomainError::WorkflowFailed {
+                        process: workflow.name().to_string(),
+                        node: instance.steps.last().map(|step| step.name.clone()),
+                        message: message.clone(),
+                    });
+                }
+                if let Some(e) = error {
+                    instance.report(e);
+                }
+            }
+            Err(e) => instance.fail(e),
+        }
+        println!(
+            "✅ BPMN: Workflow completed ({} v{}, {:?})",
+            instance.workflow, active.version, instance.outcome
+        );
+        db.record(DomainEvent::WorkflowCompleted { instance })?;
+        run
+    }
+
+    // Execute Create Trade workflow using BPMN
+    pub fn create_trade(&self, db: DbPool, input: &CreateTradeInput) -> DomainResult<Trade> {
+        println!("🔄 BPMN: Starting Create Trade workflow");
+
+        // Prepare workflow context
+        let instance_id = Uuid::new_v4().to_string();
+        let ctx = TradeWorkflowCtx {
+            trader_id: input.trader_id.to_string(),
+            symbol: input.symbol.clone(),
+            direction: format!("{:?}", input.direction),
+            entry_price: input.entry_price,
+            quantity: input.quantity,
+            stop_loss: input.stop_loss,
+            take_profit: input.take_profit,
+            trade_id: String::new(),
+            instance_id: instance_id.clone(),
+            is_valid: false,
+            error: None,
+            db: Some(db.clone()),
+            steps: Vec::new(),
+        };
+        let audit_input = serde_json::json!({
+            "traderId": input.trader_id,
+            "symbol": input.symbol,
+            "direction": input.direction,
+            "entryPrice": input.entry_price,
+            "quantity": input.quantity,
+            "stopLoss": input.stop_loss,
+            "takeProfit": input.take_profit,
+        });
+
+        // Execute workflow
+        let result = self.execute(&db, &self.create_trade, instance_id, audit_input, ctx)?;
+
+        // Retrieve created trade from DB
+        if !result.trade_id.is_empty()
+            && let Some(trade) = db.get_trade(&result.trade_id)?
+        {
+            return Ok(trade);
+        }
+
+        Err(result
+            .error
+            .unwrap_or_else(|| incomplete("create_trade", "Trade creation failed")))
+    }
+
+    // Execute Copy Trader workflow using BPMN
+    pub fn copy_trader(
+        &self,
+        db: DbPool,
+        prices: PricePool,
+        input: &CopyTraderInput,
+    ) -> DomainResult<CopyRelation> {
+        println!("🔄 BPMN: Starting Copy Trader workflow");
+
+        // Prepare workflow context
+        let instance_id = Uuid::new_v4().to_string();
+        let ctx = CopyWorkflowCtx {
+            follower_id: input.follower_id.to_string(),
+            trader_id: input.trader_id.to_string(),
+            copy_ratio: input.copy_ratio,
+            sizing_mode: input.sizing_mode,
+            notional: input.notional,
+            allocated_capital: input.allocated_capital,
+            stop_loss_pct: input.stop_loss_pct,
+            take_profit_pct: input.take_profit_pct,
+            limits: input.limits.clone(),
+            filters: input.filters.clone(),
+            copy_open_positions: input.copy_open_positions,
+            relation_id: String::new(),
+            instance_id: instance_id.clone(),
+            is_valid: false,
+            error: None,
+            db: Some(db.clone()),
+            prices: Some(prices),
+            steps: Vec::new(),
+        };
+        let audit_input = serde_json::json!({
+            "followerId": input.follower_id,
+            "traderId": input.trader_id,
+            "copyRatio": input.copy_ratio,
+            "sizingMode": input.sizing_mode,
+            "notional": input.notional,
+            "allocatedCapital": input.allocated_capital,
+            "stopLossPct": input.stop_loss_pct,
+            "takeProfitPct": input.take_profit_pct,
+            "limits": input.limits,
+            "filters": input.filters,
+            "copyOpenPositions": input.copy_open_positions,
+        });
+
+        // Execute workflow
+        let result = self.execute(&db, &self.copy_trader, instance_id, audit_input, ctx)?;
+
+        // Retrieve created relation from DB
+        if !result.relation_id.is_empty()
+            && let Some(relation) = db.get_copy_relation(&result.relation_id)?
+        {
+            return Ok(relation);
+        }
+
+        Err(result
+            .error
+            .unwrap_or_else(|| incomplete("copy_trader", "Copy failed")))
+    }
+
+    // Execute Close Trade workflow using BPMN
+    pub fn close_trade(
+        &self,
+        db: DbPool,
+        actor_id: &str,
+        trade_id: &str,
+        exit_price: f64,
+    ) -> DomainResult<Trade> {
+        println!("🔄 BPMN: Starting Close Trade workflow");
+
+        let ctx = CloseTradeWorkflowCtx {
+            trade_id: trade_id.to_string(),
+            actor_id: actor_id.to_string(),
+            exit_price,
+            is_valid: false,
+            error: None,
+            db: Some(db.clone()),
+            steps: Vec::new(),
+        };
+        let audit_input = serde_json::json!({ "tradeId": trade_id, "exitPrice": exit_price });
+
+        let instance_id = Uuid::new_v4().to_string();
+        let result = self.execute(&db, &self.close_trade, instance_id, audit_input, ctx)?;
+
+        if result.is_valid
+            && result.error.is_none()
+            && let Some(trade) = db.get_trade(&result.trade_id)?
+        {
+            return Ok(trade);
+        }
+
+        Err(result
+            .error
+            .unwrap_or_else(|| incomplete("close_trade", "Close failed")))
+    }
+
+    // Execute Stop Copying workflow using BPMN
+    pub fn stop_copying(
+        &self,
+        db: DbPool,
+        prices: PricePool,
+        actor_id: &str,
+        relation_id: &str,
+        mode: StopCopyMode,
+    ) -> DomainResult<CopyRelation> {
+        println!("🔄 BPMN: Starting Stop Copying workflow");
+
+        let ctx = StopCopyingWorkflowCtx {
+            relation_id: relation_id.to_string(),
+            actor_id: actor_id.to_string(),
+            trader_id: String::new(),
+            mode,
+            is_valid: false,
+            error: None,
+            db: Some(db.clone()),
+            prices: Some(prices),
+            steps: Vec::new(),
+        };
+        let audit_input = serde_json::json!({ "relationId": relation_id, "mode": mode });
+
+        let instance_id = Uuid::new_v4().to_string();
+        let result = self.execute(&db, &self.stop_copying, instance_id, audit_input, ctx)?;
+
+        if result.is_valid
+            && result.error.is_none()
+            && let Some(relation) = db.get_copy_relation(&result.relation_id)?
+        {
+            return Ok(relation);
+        }
+
+        Err(result
+            .error
+            .unwrap_or_else(|| incomplete("stop_copying", "Stop copying failed")))
+    }
+
+    // Execute Register User workflow using BPMN
+    pub fn register_user(
+        &self,
+        db: DbPool,
+        username: &str,
+        password: &str,
+        is_trader: bool,
+    ) -> DomainResult<User> {
+        println!("🔄 BPMN: Starting Register User workflow");
+
+        let ctx = RegisterUserWorkflowCtx {
+            username: username.trim().to_string(),
+            password: password.to_string(),
+            is_trader,
+            user_id: String::new(),
+            is_valid: false,
+            error: None,
+            db: Some(db.clone()),
+            steps: Vec::new(),
+        };
+        let audit_input = serde_json::json!({ "username": username, "isTrader": is_trader });
+
+        let instance_id = Uuid::new_v4().to_string();
+        let result = self.execute(&db, &self.register_user, instance_id, audit_input, ctx)?;
+
+        if !result.user_id.is_empty()
+            && let Some(user) = db.get_user(&result.user_id)?
+        {
+            return Ok(user);
+        }
+
+        Err(result
+            .error
+            .unwrap_or_else(|| incomplete("register_user", "Registration failed")))
+    }
+}
+
+// Name of the end event of a successful run in every diagram
+const SUCCESS_END: &str = "Success";
+
+// Task result that leaves through the task's error boundary event to the
+// Failed end event
+const ERROR: TaskResult = Some(Boundary::Symbol(Symbol::Error));
+
+// A run that ended without an error but also without its result
+fn incomplete(process: &str, message: &str) -> DomainError {
+    DomainError::WorkflowFailed {
+        process: process.to_string(),
+        node: None,
+        message: message.to_string(),
+    }
+}
+
+// Diagram node named in an engine error, when there is one
+fn failed_node(error: &snurr::Error) -> Option<String> {
+    match error {
+        snurr::Error::MissingOutput(_, node)
+        | snurr::Error::MissingImplementation(_, node)
+        | snurr::Error::MissingDefault(_, node)
+        | snurr::Error::MissingIntermediateEvent(_, node, _)
+        | snurr::Error::MissingBoundary(_, node) => Some(node.clone()),
+        _ => None,
+    }
+}
+
+// ================= BPMN Task Handlers =================
+
+fn create_trade_handlers(process: Traced<TradeWorkflowCtx>) -> Traced<TradeWorkflowCtx> {
+    process
+        // Validate trade input (symbol, quantity, prices, trader and margin)
+        .task("Validate Trade Input", |ctx| {
+            println!("  📋 Task: Validate Trade Input");
+            let mut guard = ctx.lock().unwrap();
+            let checked = validate_trade(&guard);
+            guard.is_valid = checked.is_ok();
+            guard.error = checked.err();
+            println!(
+                "    ✅ Validation: {}",
+                if guard.is_valid { "PASSED" } else { "FAILED" }
+            );
+            None
+        })
+        // Conditional gateway: proceed only if input is valid
+        .exclusive("Is Valid", |ctx| {
+            let guard = ctx.lock().unwrap();
+            if guard.is_valid { "Yes" } else { "No" }.into()
+        })
+        // Create trade record in DB
+        .task("Create Trade Record", |ctx| {
+            println!("  💾 Task: Create Trade Record");
+            let mut guard = ctx.lock().unwrap();
+            let trade_id = Uuid::new_v4().to_string();
+            let direction = parse_direction(&guard.direction);
+            let trade = Trade {
+                id: ID(trade_id.clone()),
+                trader_id: ID(guard.trader_id.clone()),
+                symbol: guard.symbol.clone(),
+                direction,
+                entry_price: guard.entry_price,
+                exit_price: None,
+                quantity: guard.quantity,
+                margin: accounting::margin_for(guard.entry_price, guard.quantity),
+                stop_loss: guard.stop_loss,
+                take_profit: guard.take_profit,
+                pnl: None,
+                realized_pnl: 0.0,
+                status: TradeStatus::Open,
+                close_reason: None,
+                workflow_instance_id: Some(ID(guard.instance_id.clone())),
+                created_at: Utc::now(),
+                closed_at: None,
+            };
+            if let Some(db) = guard.db.clone() {
+                // Reserve the margin first so a failed debit never leaves an unfunded trade
+                let opened =
+                    accounting::reserve_margin(&db, &guard.trader_id, trade.margin, &trade_id)
+                        .and_then(|_| Ok(db.record(DomainEvent::TradeOpened { trade })?));
+                if let Err(e) = opened {
+                    guard.error = Some(e);
+                    return None;
+                }
+            }
+            guard.trade_id = trade_id.clone();
+            println!("    ✅ Trade created: {}", trade_id);
+            None
+        })
+        // Conditional gateway: copy only a trade that was stored and funded
+        .exclusive("Trade Created", |ctx| {
+            let guard = ctx.lock().unwrap();
+            if guard.error.is_none() && !guard.trade_id.is_empty() {
+                "Yes"
+            } else {
+                "No"
+            }
+            .into()
+        })
+        // Copy trade to active followers, sized per relation
+        .task("Copy Trade To Followers", |ctx| {
+            println!("  👥 Task: Copy Trade To Followers");
+            let mut guard = ctx.lock().unwrap();
+            if let Some(db) = guard.db.clone() {
+                match copy_to_followers(&db, &guard.trade_id) {
+                    Ok((opened, skipped)) => {
+                        println!("    ✅ Copied to {} followers, {} skipped", opened, skipped)
+                    }
+                    Err(e) => {
+                        guard.error = Some(e);
+                        return ERROR;
+                    }
+                }
+            }
+            None
+        })
+}
+
+// Open a copy of the trade for every active follower; returns (opened, skipped)
+fn copy_to_followers(db: &Store, trade_id: &str) -> DomainResult<(usize, usize)> {
+    // Hold positions so each relation's limits are checked against the copies
+    // other trades are opening, and the trade cannot close halfway through
+    let _positions = db.lock_positions();
+    let trade = db
+        .get_trade(trade_id)?
+        .ok_or_else(|| DomainError::not_found("Trade", trade_id))?;
+    if trade.status != TradeStatus::Open {
+        return Ok((0, 0));
+    }
+    let trader = require_trader(db, &trade.trader_id)?;
+    let (mut opened, mut skipped) = (0, 0);
+    for relation in db.copy_relations_by_trader(&trade.trader_id)? {
+        // Paused relations stay attached but copy nothing new
+        if relation.status != CopyStatus::Active {
+            continue;
+        }
+        let copy = copying::open_copy(db, &relation, &trade, &trader, trade.entry_price)?;
+        if copy.status == TradeStatus::Skipped {
+            skipped += 1;
+        } else {
+            opened += 1;
+        }
+    }
+    Ok((opened, skipped))
+}
+
+// Relation the copy request describes, before it is stored
+fn new_relation(ctx: &CopyWorkflowCtx, relation_id: &str) -> CopyRelation {
+    CopyRelation {
+        id: ID(relation_id.to_string()),
+        follower_id: ID(ctx.follower_id.clone()),
+        trader_id: ID(ctx.trader_id.clone()),
+        copy_ratio: ctx.copy_ratio,
+        sizing_mode: ctx.sizing_mode,
+        notional: ctx.notional,
+        allocated_capital: ctx.allocated_capital,
+        stop_loss_pct: ctx.stop_loss_pct,
+        take_profit_pct: ctx.take_profit_pct,
+        limits: ctx.limits.clone(),
+        filters: ctx.filters.clone(),
+        status: CopyStatus::Active,
+        stop_mode: None,
+        settings_history: Vec::new(),
+        drawdown_baseline: 0.0,
+        workflow_instance_id: Some(ID(ctx.instance_id.clone())),
+        created_at: Utc::now(),
+    }
+}
+
+fn copy_trader_handlers(process: Traced<CopyWorkflowCtx>) -> Traced<CopyWorkflowCtx> {
+    process
+        // Validate copy request (ratio 0.01..1.0, both users, no self or duplicate copy)
+        .task("Validate Copy Request", |ctx| {
+            println!("  📋 Task: Validate Copy Request");
+            let mut guard = ctx.lock().unwrap();
+            let checked = validate_copy(&guard);
+            guard.is_valid = checked.is_ok();
+            guard.error = checked.err();
+            println!(
+                "    ✅ Validation: {}",
+                if guard.is_valid { "PASSED" } else { "FAILED" }
+            );
+            None
+        })
+        // Conditional gateway: proceed only if valid
+        .exclusive("Is Valid", |ctx| {
+            let guard = ctx.lock().unwrap();
+            if guard.is_valid { "Yes" } else { "No" }.into()
+        })
+        // Create copy relation in DB
+        .task("Create Copy Relation", |ctx| {
+            println!("  💾 Task: Create Copy Relation");
+            let mut guard = ctx.lock().unwrap();
+            let relation_id = Uuid::new_v4().to_string();
+            let relation = new_relation(&guard, &relation_id);
+            if let Some(db) = guard.db.clone() {
+                // Re-check under the relations lock so two requests cannot both start copying
+                let _relations = db.lock_relations();
+                let created = already_copying(&db, &guard.follower_id, &guard.trader_id).and_then(
+                    |copying| {
+                        if copying {
+                            Err(DomainError::conflict("Already copying this trader"))
+                        } else {
+                            Ok(db.record(DomainEvent::CopyStarted { relation })?)
+                        }
+                    },
+                );
+                if let Err(e) = created {
+                    guard.error = Some(e);
+                    return ERROR;
+                }
+            }
+            guard.relation_id = relation_id.clone();
+            println!("    ✅ Relation created: {}", relation_id);
+            None
+        })
+        // Update trader's follower count for the new relation
+        .task("Update Follower Count", |ctx| {
+            println!("  📊 Task: Update Follower Count");
+            let mut guard = ctx.lock().unwrap();
+            if let Some(db) = guard.db.clone() {
+                let _accounts = db.lock_accounts();
+                let updated = db
+                    .get_user(&guard.trader_id)
+                    .and_then(|trader| match trader {
+                        Some(mut trader) => {
+                            trader.followers_count += 1;
+                            println!("    ✅ Count: {}", trader.followers_count);
+                            db.record(DomainEvent::FollowerCountChanged { user: trader })
+                        }
+                        None => Ok(()),
+                    });
+                if let Err(e) = updated {
+                    guard.error = Some(e.into());
+                    return ERROR;
+                }
+            }
+            None
+        })
+        // Copy the trader's open trades only if asked
+        .exclusive("Copy Open Positions?", |ctx| {
+            let guard = ctx.lock().unwrap();
+            if guard.copy_open_positions {
+                "Yes"
+            } else {
+                "No"
+            }
+            .into()
+        })
+        // Mirror the trader's open trades at the current mark price
+        .task("Copy Open Positions", |ctx| {
+            println!("  👥 Task: Copy Open Positions");
+            let mut guard = ctx.lock().unwrap();
+            if let (Some(db), Some(prices)) = (guard.db.clone(), guard.prices.clone()) {
+                match copy_open_positions(&db, &prices, &guard.relation_id) {
+                    Ok((opened, skipped)) => {
+                        println!("    ✅ Copied {} open trades, {} skipped", opened, skipped)
+                    }
+                    Err(e) => {
+                        guard.error = Some(e);
+                        return ERROR;
+                    }
+                }
+            }
+            None
+        })
+}
+
+// Copy every open trade of the relation's trader; returns (opened, skipped)
+fn copy_open_positions(
+    db: &Store,
+    prices: &PricePool,
+    relation_id: &str,
+) -> DomainResult<(usize, usize)> {
+    // Hold positions so none of the trades closes while it is being copied
+    let _positions = db.lock_positions();
+    let relation = db
+        .get_copy_relation(relation_id)?
+        .ok_or_else(|| DomainError::not_found("CopyRelation", relation_id))?;
+    let trader = require_trader(db, &relation.trader_id)?;
+    let (mut opened, mut skipped) = (0, 0);
+    let mut trades = db.trades_by_trader(&relation.trader_id)?;
+    trades.retain(|t| t.status == TradeStatus::Open);
+    trades.sort_by_key(|t| t.created_at);
+    for trade in trades {
+        let copy = copying::open_copy_at_mark(db, prices, &relation, &trade, &trader)?;
+        if copy.status == TradeStatus::Skipped {
+            skipped += 1;
+        } else {
+            opened += 1;
+        }
+    }
+    Ok((opened, skipped))
+}
+
+fn close_trade_handlers(process: Traced<CloseTradeWorkflowCtx>) -> Traced<CloseTradeWorkflowCtx> {
+    process
+        // Validate close request (positive price, own trade that is still open)
+        .task("Validate Close Request", |ctx| {
+            println!("  📋 Task: Validate Close Request");
+            let mut guard = ctx.lock().unwrap();
+            guard.is_valid = false;
+            if guard.exit_price <= 0.0 {
+                guard.error = Some(DomainError::invalid("exitPrice", "Invalid price"));
+            } else if let Some(db) = guard.db.clone() {
+                match db.get_trade(&guard.trade_id) {
+                    Ok(Some(trade)) if trade.status == TradeStatus::Closed => {
+                        guard.error = Some(DomainError::conflict("Trade already closed"));
+                    }
+                    Ok(Some(trade)) => {
+                        let owned = auth::ensure_owner(
+                            &trade.trader_id,
+                            &guard.actor_id.as_str().into(),
+                            "Trade",
+                        );
+                        guard.is_valid = owned.is_ok();
+                        guard.error = owned.err();
+                    }
+                    Ok(None) => {
+                        guard.error = Some(DomainError::not_found("Trade", &guard.trade_id));
+                    }
+                    Err(e) => guard.error = Some(e.into()),
+                }
+            }
+            println!(
+                "    ✅ Validation: {}",
+                if guard.is_valid { "PASSED" } else { "FAILED" }
+            );
+            None
+        })
+        // Conditional gateway: proceed only if valid
+        .exclusive("Is Valid", |ctx| {
+            let guard = ctx.lock().unwrap();
+            if guard.is_valid { "Yes" } else { "No" }.into()
+        })
+        // Settle the trader and close every open copy
+        .task("Close Trade And Copies", |ctx| {
+            println!("  💾 Task: Close Trade And Copies");
+            let mut guard = ctx.lock().unwrap();
+            if let Some(db) = guard.db.clone()
+                && let Err(e) = trading::close_trade(
+                    &db,
+                    &guard.trade_id,
+                    guard.exit_price,
+                    CloseReason::Manual,
+                )
+            {
+                guard.error = Some(e);
+                return ERROR;
+            }
+            println!("    ✅ Trade closed: {}", guard.trade_id);
+            None
+        })
+}
+
+fn stop_copying_handlers(
+    process: Traced<StopCopyingWorkflowCtx>,
+) -> Traced<StopCopyingWorkflowCtx> {
+    process
+        // Validate stop request (own relation that is still active; with mode
+        // CLOSE, every open copy needs a market price)
+        .task("Validate Stop Request", |ctx| {
+            println!("  📋 Task: Validate Stop Request");
+            let mut guard = ctx.lock().unwrap();
+            guard.is_valid = false;
+            if let Some(db) = guard.db.clone() {
+                match db.get_copy_relation(&guard.relation_id) {
+                    Ok(Some(relation)) if relation.status == CopyStatus::Stopped => {
+                        guard.error = Some(DomainError::conflict("Copy relation already stopped"));
+                    }
+                    Ok(Some(relation)) => {
+                        guard.trader_id = relation.trader_id.to_string();
+                        let checked = auth::ensure_owner(
+                            &relation.follower_id,
+                            &guard.actor_id.as_str().into(),
+                            "Copy relation",
+                        )
+                        .and_then(|_| match (guard.mode, &guard.prices) {
+                            (StopCopyMode::Close, Some(prices)) => {
+                                copying::close_prices(&db, prices, &relation).map(|_| ())
+                            }
+                            _ => Ok(()),
+                        });
+                        guard.is_valid = checked.is_ok();
+                        guard.error = checked.err();
+                    }
+                    Ok(None) => {
+                        guard.error =
+                            Some(DomainError::not_found("CopyRelation", &guard.relation_id));
+                    }
+                    Err(e) => guard.error = Some(e.into()),
+                }
+            }
+            println!(
+                "    ✅ Validation: {}",
+                if guard.is_valid { "PASSED" } else { "FAILED" }
+            );
+            None
+        })
+        // Conditional gateway: proceed only if valid
+        .exclusive("Is Valid", |ctx| {
+            let guard = ctx.lock().unwrap();
+            if guard.is_valid { "Yes" } else { "No" }.into()
+        })
+        // Mark the relation inactive and record how its open copies are handled
+        .task("Deactivate Copy Relation", |ctx| {
+            println!("  💾 Task: Deactivate Copy Relation");
+            let mut guard = ctx.lock().unwrap();
+            if let Some(db) = guard.db.clone() {
+                let mode = guard.mode;
+                // Re-check under the relations lock so two requests cannot both stop it
+                let _relations = db.lock_relations();
+                let stopped = stopped_relation(&db, &guard.relation_id).and_then(|mut relation| {
+                    if relation.status == CopyStatus::Stopped {
+                        return Err(DomainError::conflict("Copy relation already stopped"));
+                    }
+                    relation.status = CopyStatus::Stopped;
+                    relation.stop_mode = Some(mode);
+                    Ok(db.record(DomainEvent::CopyStopped { relation })?)
+                });
+                if let Err(e) = stopped {
+                    guard.error = Some(e);
+                    return ERROR;
+                }
+            }
+            println!("    ✅ Relation stopped: {}", guard.relation_id);
+            None
+        })
+        // Update trader's follower count for the stopped relation
+        .task("Update Follower Count", |ctx| {
+            println!("  📊 Task: Update Follower Count");
+            let mut guard = ctx.lock().unwrap();
+            if let Some(db) = guard.db.clone() {
+                let _accounts = db.lock_accounts();
+                let updated = db
+                    .get_user(&guard.trader_id)
+                    .and_then(|trader| match trader {
+                        Some(mut trader) => {
+                            trader.followers_count = (trader.followers_count - 1).max(0);
+                            println!("    ✅ Count: {}", trader.followers_count);
+                            db.record(DomainEvent::FollowerCountChanged { user: trader })
+                        }
+                        None => Ok(()),
+                    });
+                if let Err(e) = updated {
+                    guard.error = Some(e.into());
+                    return ERROR;
+                }
+            }
+            None
+        })
+        // Branch on the requested handling of open copies. Failed runs never get
+        // here: the tasks before it leave through their error boundaries.
+        .exclusive("Open Copies", |ctx| {
+            let guard = ctx.lock().unwrap();
+            match guard.mode {
+                StopCopyMode::Close => "Close",
+                StopCopyMode::Keep => "Keep",
+                StopCopyMode::Detach => "Detach",
+            }
+            .into()
+        })
+        // Close open copies at the mark price
+        .task("Close Open Copies", |ctx| {
+            println!("  🔒 Task: Close Open Copies");
+            let mut guard = ctx.lock().unwrap();
+            if let (Some(db), Some(prices)) = (guard.db.clone(), guard.prices.clone()) {
+                let closed = stopped_relation(&db, &guard.relation_id)
+                    .and_then(|relation| copying::close_open_copies(&db, &prices, &relation));
+                match closed {
+                    Ok(count) => println!("    ✅ Closed {} copies", count),
+                    Err(e) => {
+                        guard.error = Some(e);
+                        return ERROR;
+                    }
+                }
+            }
+            None
+        })
+        // Unlink open copies so the follower manages them
+        .task("Detach Open Copies", |ctx| {
+            println!("  ✂️ Task: Detach Open Copies");
+            let mut guard = ctx.lock().unwrap();
+            if let Some(db) = guard.db.clone() {
+                let detached = stopped_relation(&db, &guard.relation_id)
+                    .and_then(|relation| copying::detach_open_copies(&db, &relation));
+                match detached {
+                    Ok(count) => println!("    ✅ Detached {} copies", count),
+                    Err(e) => {
+                        guard.error = Some(e);
+                        return ERROR;
+                    }
+                }
+            }
+            None
+        })
+}
+
+fn stopped_relation(db: &Store, relation_id: &str) -> DomainResult<CopyRelation> {
+    db.get_copy_relation(relation_id)?
+        .ok_or_else(|| DomainError::not_found("CopyRelation", relation_id))
+}
+
+fn register_user_handlers(
+    process: Traced<RegisterUserWorkflowCtx>,
+) -> Traced<RegisterUserWorkflowCtx> {
+    process
+        // Validate registration (username present, not too long, not taken;
+        // password of acceptable length)
+        .task("Validate Registration", |ctx| {
+            println!("  📋 Task: Validate Registration");
+            let mut guard = ctx.lock().unwrap();
+            guard.is_valid = false;
+            if guard.username.is_empty() {
+                guard.error = Some(DomainError::invalid("username", "Username is required"));
+            } else if guard.username.chars().count() > MAX_USERNAME_LEN {
+                guard.error = Some(DomainError::invalid("username", "Username is too long"));
+            } else if guard.password.chars().count() < auth::MIN_PASSWORD_LEN {
+                guard.error = Some(DomainError::invalid("password", "Password is too short"));
+            } else if guard.password.chars().count() > auth::MAX_PASSWORD_LEN {
+                guard.error = Some(DomainError::invalid("password", "Password is too long"));
+            } else if let Some(db) = guard.db.clone() {
+                match username_taken(&db, &guard.username) {
+                    Ok(true) => guard.error = Some(DomainError::conflict("Username already taken")),
+                    Ok(false) => guard.is_valid = true,
+                    Err(e) => guard.error = Some(e),
+                }
+            }
+            println!(
+                "    ✅ Validation: {}",
+                if guard.is_valid { "PASSED" } else { "FAILED" }
+            );
+            None
+        })
+        // Conditional gateway: proceed only if valid
+        .exclusive("Is Valid", |ctx| {
+            let guard = ctx.lock().unwrap();
+            if guard.is_valid { "Yes" } else { "No" }.into()
+        })
+        // Create the account with the starting balance and hashed password
+        .task("Create User Account", |ctx| {
+            println!("  💾 Task: Create User Account");
+            let mut guard = ctx.lock().unwrap();
+            if let Some(db) = guard.db.clone() {
+                let password_hash = match auth::hash_password(&guard.password) {
+                    Ok(hash) => hash,
+                    Err(e) => {
+                        guard.error = Some(e);
+                        return ERROR;
+                    }
+                };
+                // Re-check under the accounts lock so two registrations cannot share a name
+                let _accounts = db.lock_accounts();
+                let mut user = User::new(
+                    &Uuid::new_v4().to_string(),
+                    &guard.username,
+                    STARTING_BALANCE,
+                    guard.is_trader,
+                );
+                user.password_hash = Some(password_hash);
+                let created = username_taken(&db, &guard.username).and_then(|taken| {
+                    if taken {
+                        Err(DomainError::conflict("Username already taken"))
+                    } else {
+                        Ok(db.record(DomainEvent::UserRegistered { user: user.clone() })?)
+                    }
+                });
+                match created {
+                    Ok(()) => {
+                        guard.user_id = user.id.to_string();
+                        println!("    ✅ User created: {}", guard.user_id);
+                    }
+                    Err(e) => {
+                        guard.error = Some(e);
+                        return ERROR;
+                    }
+                }
+            }
+            None
+        })
+}
+
+// Balance credited to newly registered users
+const STARTING_BALANCE: f64 = 10000.0;
+const MAX_USERNAME_LEN: usize = 32;
+
+// ================= Validation =================
+
+fn validate_trade(ctx: &TradeWorkflowCtx) -> DomainResult<()> {
+    if !trading::valid_symbol(&ctx.symbol) {
+        return Err(DomainError::invalid("symbol", "Invalid symbol"));
+    }
+    if ctx.quantity <= 0.0 {
+        return Err(DomainError::invalid("quantity", "Invalid quantity"));
+    }
+    if ctx.entry_price <= 0.0 {
+        return Err(DomainError::invalid("entryPrice", "Invalid price"));
+    }
+    trading::validate_levels(
+        parse_direction(&ctx.direction),
+        ctx.entry_price,
+        ctx.stop_loss,
+        ctx.take_profit,
+    )?;
+    if let Some(db) = &ctx.db {
+        // Trader must be able to cover the margin for the position
+        let trader = require_trader(db, &ctx.trader_id)?;
+        let margin = accounting::margin_for(ctx.entry_price, ctx.quantity);
+        if trader.balance < margin {
+            return Err(DomainError::InsufficientFunds {
+                required: margin,
+                available: trader.balance,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn validate_copy(ctx: &CopyWorkflowCtx) -> DomainResult<()> {
+    copying::validate_settings(&new_relation(ctx, ""))?;
+    if ctx.follower_id == ctx.trader_id {
+        return Err(DomainError::invalid("traderId", "Cannot copy yourself"));
+    }
+    if let Some(db) = &ctx.db {
+        if db.get_user(&ctx.follower_id)?.is_none() {
+            return Err(DomainError::not_found("User", &ctx.follower_id));
+        }
+        require_trader(db, &ctx.trader_id)?;
+        if already_copying(db, &ctx.follower_id, &ctx.trader_id)? {
+            return Err(DomainError::conflict("Already copying this trader"));
+        }
+    }
+    Ok(())
+}
+
+// The user behind `trader_id`, which must exist and be registered as a trader
+pub fn require_trader(db: &Store, trader_id: &str) -> DomainResult<User> {
+    let user = db
+        .get_user(trader_id)?
+        .ok_or_else(|| DomainError::not_found("User", trader_id))?;
+    if !user.is_trader {
+        return Err(DomainError::invalid("traderId", "User is not a trader"));
+    }
+    if user.suspended {
+        return Err(DomainError::invalid("traderId", "Trader is suspended"));
+    }
+    Ok(user)
+}
+
+// Whether the follower already has an active relation to the trader
+fn already_copying(db: &Store, follower_id: &str, trader_id: &str) -> DomainResult<bool> {
+    Ok(db
+        .copy_relations_by_follower(follower_id)?
+        .iter()
+        .any(|r| r.status.is_following() && r.trader_id.as_str() == trader_id))
+}
+
+// Usernames are unique, ignoring case
+fn username_taken(db: &Store, username: &str) -> DomainResult<bool> {
+    Ok(db
+        .list_users()?
+        .iter()
+        .any(|u| u.username.eq_ignore_ascii_case(username)))
+}
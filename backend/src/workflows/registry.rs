@@ -0,0 +1,399 @@
+use super::WorkflowPool;
+use super::audit::{Traceable, Traced};
+use super::diagram::EndEvents;
+use crate::errors::{DomainError, DomainResult};
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use notify::{EventKind, RecursiveMode, Watcher};
+use parking_lot::RwLock;
+use snurr::{Process, Run};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+// ================= Versioned Definitions =================
+
+// One loaded version of a workflow diagram, as exposed over GraphQL
+#[derive(Debug, Clone, SimpleObject)]
+pub struct WorkflowDefinition {
+    pub name: String,
+    pub version: u32,
+    pub active: bool,
+    // FNV-1a of the diagram XML, to tell versions apart
+    pub checksum: String,
+    pub loaded_at: DateTime<Utc>,
+    // Runs started on this version
+    pub runs: u64,
+}
+
+struct Version<T> {
+    version: u32,
+    checksum: String,
+    loaded_at: DateTime<Utc>,
+    process: Arc<Process<T, Run>>,
+    ends: Arc<EndEvents>,
+    runs: AtomicU64,
+}
+
+// The version a new run starts on
+pub struct Active<T> {
+    pub version: u32,
+    pub checksum: String,
+    pub process: Arc<Process<T, Run>>,
+    pub ends: Arc<EndEvents>,
+}
+
+struct History<T> {
+    versions: Vec<Version<T>>,
+    active: usize,
+}
+
+// Every version of one workflow built so far. New runs use the active version;
+// a run holds its own handle to the process, so reloading or rolling back never
+// disturbs a run already in flight.
+pub struct Versioned<T> {
+    name: &'static str,
+    path: PathBuf,
+    handlers: fn(Traced<T>) -> Traced<T>,
+    history: RwLock<History<T>>,
+}
+
+impl<T: Traceable + 'static> Versioned<T> {
+    // Build version 1 from `<dir>/<name>.bpmn`
+    pub fn load(
+        dir: &Path,
+        name: &'static str,
+        handlers: fn(Traced<T>) -> Traced<T>,
+    ) -> Result<Self, String> {
+        let path = dir.join(format!("{}.bpmn", name));
+        let (checksum, process, ends) = compile(&path, handlers)?;
+        Ok(Versioned {
+            name,
+            path,
+            handlers,
+            history: RwLock::new(History {
+                versions: vec![Version {
+                    version: 1,
+                    checksum,
+                    loaded_at: Utc::now(),
+                    process: Arc::new(process),
+                    ends: Arc::new(ends),
+                    runs: AtomicU64::new(0),
+                }],
+                active: 0,
+            }),
+        })
+    }
+
+    // Active version, counting a new run against it
+    pub fn active(&self) -> Active<T> {
+        let history = self.history.read();
+        let current = &history.versions[history.active];
+        current.runs.fetch_add(1, Ordering::Relaxed);
+        Active {
+            version: current.version,
+            checksum: current.checksum.clone(),
+            process: current.process.clone(),
+            ends: current.ends.clone(),
+        }
+    }
+}
+
+// Type-erased view of a Versioned<T> so the registry can handle every workflow alike
+pub trait DefinitionSlot: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Rebuild from disk and activate the result as a new version.
+    // Returns None when the diagram matches the newest version, so a rollback
+    // stays in place until the file really changes.
+    fn reload(&self) -> Result<Option<u32>, String>;
+
+    // Make an earlier (or later) version active again
+    fn rollback(&self, version: u32) -> DomainResult<WorkflowDefinition>;
+
+    // Every version, newest first
+    fn definitions(&self) -> Vec<WorkflowDefinition>;
+}
+
+impl<T: Traceable + 'static> DefinitionSlot for Versioned<T>
+where
+    Process<T, Run>: Send + Sync,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn reload(&self) -> Result<Option<u32>, String> {
+        let (checksum, process, ends) = compile(&self.path, self.handlers)?;
+        let mut history = self.history.write();
+        if history
+            .versions
+            .last()
+            .is_some_and(|newest| newest.checksum == checksum)
+        {
+            return Ok(None);
+        }
+        let version = history.versions.len() as u32 + 1;
+        history.versions.push(Version {
+            version,
+            checksum,
+            loaded_at: Utc::now(),
+            process: Arc::new(process),
+            ends: Arc::new(ends),
+            runs: AtomicU64::new(0),
+        });
+        history.active = history.versions.len() - 1;
+        Ok(Some(version))
+    }
+
+    fn rollback(&self, version: u32) -> DomainResult<WorkflowDefinition> {
+        let missing =
+            || DomainError::not_found("WorkflowDefinition", &format!("{} v{}", self.name, version));
+        let mut history = self.history.write();
+        let index = history
+            .versions
+            .iter()
+            .position(|v| v.version == version)
+            .ok_or_else(missing)?;
+        history.active = index;
+        drop(history);
+        println!("⏪ Workflow {} rolled back to v{}", self.name, version);
+        self.definitions()
+            .into_iter()
+            .find(|d| d.version == version)
+            .ok_or_else(missing)
+    }
+
+    fn definitions(&self) -> Vec<WorkflowDefinition> {
+        let history = self.history.read();
+        history
+            .versions
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, v)| WorkflowDefinition {
+                name: self.name.to_string(),
+                version: v.version,
+                active: index == history.active,
+                checksum: v.checksum.clone(),
+                loaded_at: v.loaded_at,
+                runs: v.runs.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+// Parse the diagram and register its handlers. Fails when a task or gateway in
+// the diagram has no handler.
+fn compile<T: Traceable + 'static>(
+    path: &Path,
+    handlers: fn(Traced<T>) -> Traced<T>,
+) -> Result<(String, Process<T, Run>, EndEvents), String> {
+    let xml = std::fs::read_to_string(path)
+        .map_err(|e| format!("BPMN read error in {}: {}", path.display(), e))?;
+    let process = xml
+        .parse::<Process<T>>()
+        .map_err(|e| format!("BPMN parse error in {}: {}", path.display(), e))?;
+    let process = handlers(Traced::new(process))
+        .into_inner()
+        .build()
+        .map_err(|e| format!("BPMN build error in {}: {}", path.display(), e))?;
+    let ends = EndEvents::parse(&xml)
+        .map_err(|e| format!("BPMN parse error in {}: {}", path.display(), e))?;
+    Ok((checksum(&xml), process, ends))
+}
+
+fn checksum(xml: &str) -> String {
+    let hash = xml.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+// ================= Hot Reload =================
+
+// Rebuild a workflow whenever its diagram in `dir` changes. A diagram that
+// fails to build is reported and the active version stays in place.
+pub fn spawn_watcher(workflows: WorkflowPool, dir: PathBuf) -> Result<(), String> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| format!("BPMN watcher error: {}", e))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("BPMN watcher error: {}", e))?;
+    println!("👀 Watching {} for diagram changes", dir.display());
+
+    std::thread::spawn(move || {
+        // The watcher stops when dropped, so this thread owns it
+        let _watcher = watcher;
+        while let Ok(first) = rx.recv() {
+            // Editors save in several steps; let them settle, then handle the burst once
+            std::thread::sleep(Duration::from_millis(200));
+            let mut changed: Vec<String> = Vec::new();
+            for event in std::iter::once(first).chain(rx.try_iter()) {
+                let Ok(event) = event else { continue };
+                if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    continue;
+                }
+                for path in event.paths {
+                    if path.extension().is_some_and(|ext| ext == "bpmn")
+                        && let Some(name) = path.file_stem().and_then(|s| s.to_str())
+                        && !changed.iter().any(|c| c == name)
+                    {
+                        changed.push(name.to_string());
+                    }
+                }
+            }
+            for name in changed {
+                match workflows.reload(&name) {
+                    Ok(Some(version)) => println!("🔁 Workflow {} reloaded as v{}", name, version),
+                    Ok(None) => {}
+                    Err(e) => println!("⚠️ Workflow {} not reloaded: {}", name, e),
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflows::{RegisterUserWorkflowCtx, register_user_handlers};
+
+    // register_user.bpmn copied into a fresh temp directory, removed on drop
+    struct Diagram {
+        dir: PathBuf,
+        original: String,
+    }
+
+    impl Diagram {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("bpmn-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("bpmn/register_user.bpmn");
+            let original = std::fs::read_to_string(source).unwrap();
+            let diagram = Diagram { dir, original };
+            diagram.write(&diagram.original);
+            diagram
+        }
+
+        fn write(&self, xml: &str) {
+            std::fs::write(self.dir.join("register_user.bpmn"), xml).unwrap();
+        }
+
+        // The diagram with a comment appended: same process, new checksum
+        fn edited(&self, note: &str) -> String {
+            format!("{}<!-- {} -->\n", self.original, note)
+        }
+
+        fn load(&self) -> Versioned<RegisterUserWorkflowCtx> {
+            Versioned::load(&self.dir, "register_user", register_user_handlers).unwrap()
+        }
+    }
+
+    impl Drop for Diagram {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn active_version(slot: &dyn DefinitionSlot) -> u32 {
+        slot.definitions()
+            .iter()
+            .find(|d| d.active)
+            .unwrap()
+            .version
+    }
+
+    #[test]
+    fn checksum_is_stable_fnv1a() {
+        assert_eq!(checksum(""), "cbf29ce484222325");
+        assert_eq!(checksum("a"), "af63dc4c8601ec8c");
+        assert_ne!(checksum("<a/>"), checksum("<a />"));
+    }
+
+    #[test]
+    fn reload_adds_a_version_only_when_the_diagram_changes() {
+        let diagram = Diagram::new();
+        let slot = diagram.load();
+        assert_eq!(slot.reload(), Ok(None));
+
+        diagram.write(&diagram.edited("v2"));
+        assert_eq!(slot.reload(), Ok(Some(2)));
+        assert_eq!(slot.reload(), Ok(None));
+
+        let definitions = slot.definitions();
+        assert_eq!(
+            definitions.iter().map(|d| d.version).collect::<Vec<_>>(),
+            [2, 1]
+        );
+        assert!(definitions[0].active && !definitions[1].active);
+        assert_eq!(definitions[1].checksum, checksum(&diagram.original));
+        assert_eq!(definitions[0].checksum, checksum(&diagram.edited("v2")));
+    }
+
+    #[test]
+    fn rollback_holds_until_the_diagram_differs_from_the_newest_version() {
+        let diagram = Diagram::new();
+        let slot = diagram.load();
+        diagram.write(&diagram.edited("v2"));
+        slot.reload().unwrap();
+
+        let rolled_back = slot.rollback(1).unwrap();
+        assert!(rolled_back.active);
+        assert_eq!(rolled_back.checksum, checksum(&diagram.original));
+        // The file still holds v2, so a reload changes nothing
+        assert_eq!(slot.reload(), Ok(None));
+        assert_eq!(active_version(&slot), 1);
+        // Only the newest version is compared, so restoring v1's text makes v3
+        diagram.write(&diagram.original);
+        assert_eq!(slot.reload(), Ok(Some(3)));
+        assert_eq!(active_version(&slot), 3);
+
+        assert!(matches!(
+            slot.rollback(9),
+            Err(DomainError::NotFound { .. })
+        ));
+        assert_eq!(active_version(&slot), 3);
+    }
+
+    #[test]
+    fn a_diagram_that_fails_to_build_keeps_the_active_version() {
+        let diagram = Diagram::new();
+        let slot = diagram.load();
+        // A task without a handler
+        let unknown = diagram
+            .original
+            .replace("name=\"Create User Account\"", "name=\"Send Welcome Mail\"");
+        diagram.write(&unknown);
+        assert!(slot.reload().is_err_and(|e| e.contains("build error")));
+        diagram.write("<not bpmn");
+        assert!(slot.reload().is_err());
+        assert_eq!(slot.definitions().len(), 1);
+        assert_eq!(active_version(&slot), 1);
+    }
+
+    #[test]
+    fn runs_count_against_the_version_they_started_on() {
+        let diagram = Diagram::new();
+        let slot = diagram.load();
+        let first = slot.active();
+        diagram.write(&diagram.edited("v2"));
+        slot.reload().unwrap();
+        let second = slot.active();
+        slot.active();
+
+        assert_eq!((first.version, second.version), (1, 2));
+        // A run in flight keeps the process it started with
+        assert!(!Arc::ptr_eq(&first.process, &second.process));
+        let runs: Vec<(u32, u64)> = slot
+            .definitions()
+            .iter()
+            .map(|d| (d.version, d.runs))
+            .collect();
+        assert_eq!(runs, [(2, 2), (1, 1)]);
+    }
+}
@@ -1,17 +1,35 @@
 import gql from 'graphql-tag'
 
 // Queries
-export const GET_TRADERS = gql`
-  query GetTraders {
-    traders {
+export const GET_ME = gql`
+  query GetMe {
+    me {
       id
       username
       balance
-      totalPnl
-      winRate
-      followersCount
       isTrader
-      createdAt
+    }
+  }
+`
+
+export const GET_TRADERS = gql`
+  query GetTraders {
+    traders(sort: { key: PNL, direction: DESC }) {
+      nodes {
+        id
+        username
+        balance
+        totalPnl
+        winRate
+        tradeCount
+        avgWin
+        avgLoss
+        bestTrade
+        worstTrade
+        followersCount
+        isTrader
+        createdAt
+      }
     }
   }
 `
@@ -19,31 +37,35 @@ export const GET_TRADERS = gql`
 export const GET_USERS = gql`
   query GetUsers {
     users {
-      id
-      username
-      balance
-      totalPnl
-      winRate
-      followersCount
-      isTrader
+      nodes {
+        id
+        username
+        balance
+        totalPnl
+        winRate
+        followersCount
+        isTrader
+      }
     }
   }
 `
 
 export const GET_TRADES = gql`
   query GetTrades($traderId: ID) {
-    trades(traderId: $traderId) {
-      id
-      traderId
-      symbol
-      direction
-      entryPrice
-      exitPrice
-      quantity
-      pnl
-      status
-      createdAt
-      closedAt
+    trades(filter: { traderId: $traderId }) {
+      nodes {
+        id
+        traderId
+        symbol
+        direction
+        entryPrice
+        exitPrice
+        quantity
+        pnl
+        status
+        createdAt
+        closedAt
+      }
     }
   }
 `
@@ -51,40 +73,53 @@ export const GET_TRADES = gql`
 export const GET_OPEN_TRADES = gql`
   query GetOpenTrades {
     openTrades {
-      id
-      traderId
-      symbol
-      direction
-      entryPrice
-      quantity
-      status
-      createdAt
+      nodes {
+        id
+        traderId
+        symbol
+        direction
+        entryPrice
+        quantity
+        status
+        createdAt
+      }
     }
   }
 `
 
 export const GET_MY_COPY_RELATIONS = gql`
-  query GetMyCopyRelations($followerId: ID!) {
-    myCopyRelations(followerId: $followerId) {
-      id
-      followerId
-      traderId
-      copyRatio
-      active
-      createdAt
+  query GetMyCopyRelations {
+    myCopyRelations {
+      nodes {
+        id
+        followerId
+        traderId
+        copyRatio
+        status
+        createdAt
+      }
     }
   }
 `
 
 export const GET_MY_COPIED_TRADES = gql`
-  query GetMyCopiedTrades($followerId: ID!) {
-    myCopiedTrades(followerId: $followerId) {
-      id
-      originalTradeId
-      followerId
-      quantity
-      pnl
-      status
+  query GetMyCopiedTrades {
+    myCopiedTrades {
+      nodes {
+        id
+        originalTradeId
+        relationId
+        followerId
+        symbol
+        direction
+        entryPrice
+        exitPrice
+        quantity
+        pnl
+        status
+        openedAt
+        closedAt
+      }
     }
   }
 `
@@ -106,7 +141,7 @@ export const CREATE_TRADE = gql`
 `
 
 export const CLOSE_TRADE = gql`
-  mutation CloseTrade($tradeId: ID!, $exitPrice: Float!) {
+  mutation CloseTrade($tradeId: ID!, $exitPrice: Float) {
     closeTrade(tradeId: $tradeId, exitPrice: $exitPrice) {
       id
       exitPrice
@@ -124,7 +159,7 @@ export const COPY_TRADER = gql`
       followerId
       traderId
       copyRatio
-      active
+      status
       createdAt
     }
   }
@@ -134,14 +169,14 @@ export const STOP_COPYING = gql`
   mutation StopCopying($relationId: ID!) {
     stopCopying(relationId: $relationId) {
       id
-      active
+      status
     }
   }
 `
 
 export const REGISTER_USER = gql`
-  mutation RegisterUser($username: String!, $isTrader: Boolean!) {
-    registerUser(username: $username, isTrader: $isTrader) {
+  mutation RegisterUser($username: String!, $password: String!, $isTrader: Boolean!) {
+    registerUser(username: $username, password: $password, isTrader: $isTrader) {
       id
       username
       balance
@@ -149,3 +184,17 @@ export const REGISTER_USER = gql`
     }
   }
 `
+
+export const LOGIN = gql`
+  mutation Login($username: String!, $password: String!) {
+    login(username: $username, password: $password) {
+      token
+      user {
+        id
+        username
+        balance
+        isTrader
+      }
+    }
+  }
+`
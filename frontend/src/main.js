@@ -1,6 +1,7 @@
 import { createApp, provide, h } from 'vue'
 import { createRouter, createWebHistory } from 'vue-router'
 import { ApolloClient, InMemoryCache, createHttpLink } from '@apollo/client/core'
+import { setContext } from '@apollo/client/link/context'
 import { DefaultApolloClient } from '@vue/apollo-composable'
 import App from './App.vue'
 
@@ -14,8 +15,19 @@ const httpLink = createHttpLink({
   uri: '/graphql'
 })
 
+// Send the session token from login with every request
+const authLink = setContext((_, { headers }) => {
+  const token = localStorage.getItem('token')
+  return {
+    headers: {
+      ...headers,
+      ...(token ? { Authorization: `Bearer ${token}` } : {})
+    }
+  }
+})
+
 const apolloClient = new ApolloClient({
-  link: httpLink,
+  link: authLink.concat(httpLink),
   cache: new InMemoryCache(),
   defaultOptions: {
     query: {